@@ -0,0 +1,161 @@
+use crate::models::{error::AppError, settings::AccessRules};
+
+/// アクセス制御の判定に使う、発言者とその場所の情報
+#[derive(Debug, Clone, Default)]
+pub struct AccessRequest {
+    pub user_id: u64,
+    /// メンバー情報を取得できず、ロールが分からない場合は `None`
+    pub role_ids: Option<Vec<u64>>,
+    /// 発言したチャンネルと、その親（スレッドの親チャンネルやカテゴリー）
+    pub channel_ids: Vec<u64>,
+    /// 管理者は制限を受けない。ルールの設定ミスで締め出されないようにするため
+    pub is_admin: bool,
+}
+
+/// Discordの権限（管理者・サーバー管理）か、ボット管理者ロールを持っていれば管理者
+pub fn is_bot_admin(rules: &AccessRules, has_admin_permission: bool, role_ids: &[u64]) -> bool {
+    has_admin_permission || role_ids.iter().any(|id| rules.admin_roles.contains(id))
+}
+
+/// 拒否リストを先に確認し、次に空でない許可リストに一致するかを確認する
+pub fn authorize(rules: &AccessRules, request: &AccessRequest) -> Result<(), AppError> {
+    if request.is_admin {
+        return Ok(());
+    }
+
+    let denied = |reason: &str| {
+        Err(AppError::PermissionDenied {
+            reason: reason.to_string(),
+        })
+    };
+    let any_in = |ids: &[u64], list: &[u64]| ids.iter().any(|id| list.contains(id));
+
+    if rules.deny.users.contains(&request.user_id) {
+        return denied("user is denied");
+    }
+    // ロールが分からないままロールのルールを飛ばすと拒否リストをすり抜けるので、拒否する
+    let role_ids = match &request.role_ids {
+        Some(role_ids) => role_ids.as_slice(),
+        None if rules.deny.roles.is_empty() && rules.allow.roles.is_empty() => &[],
+        None => return denied("member roles could not be resolved"),
+    };
+    if any_in(role_ids, &rules.deny.roles) {
+        return denied("role is denied");
+    }
+    if any_in(&request.channel_ids, &rules.deny.channels) {
+        return denied("channel is denied");
+    }
+
+    // ユーザーとロールはどちらかに一致すればよい
+    let restricts_members = !rules.allow.users.is_empty() || !rules.allow.roles.is_empty();
+    if restricts_members
+        && !rules.allow.users.contains(&request.user_id)
+        && !any_in(role_ids, &rules.allow.roles)
+    {
+        return denied("user is not in the allow list");
+    }
+    if !rules.allow.channels.is_empty() && !any_in(&request.channel_ids, &rules.allow.channels) {
+        return denied("channel is not in the allow list");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::settings::AccessList;
+
+    fn request(user_id: u64, role_ids: &[u64], channel_ids: &[u64]) -> AccessRequest {
+        AccessRequest {
+            user_id,
+            role_ids: Some(role_ids.to_vec()),
+            channel_ids: channel_ids.to_vec(),
+            is_admin: false,
+        }
+    }
+
+    fn list(users: &[u64], roles: &[u64], channels: &[u64]) -> AccessList {
+        AccessList {
+            users: users.to_vec(),
+            roles: roles.to_vec(),
+            channels: channels.to_vec(),
+        }
+    }
+
+    #[test]
+    fn empty_rules_allow_everyone() {
+        assert!(authorize(&AccessRules::default(), &request(1, &[], &[10])).is_ok());
+    }
+
+    #[test]
+    fn deny_takes_precedence_over_allow() {
+        let rules = AccessRules {
+            allow: list(&[1], &[], &[]),
+            deny: list(&[], &[5], &[]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            authorize(&rules, &request(1, &[5], &[10])),
+            Err(AppError::PermissionDenied { .. })
+        ));
+    }
+
+    #[test]
+    fn allowed_role_or_user_is_enough() {
+        let rules = AccessRules {
+            allow: list(&[1], &[5], &[]),
+            ..Default::default()
+        };
+        assert!(authorize(&rules, &request(1, &[], &[10])).is_ok());
+        assert!(authorize(&rules, &request(2, &[5], &[10])).is_ok());
+        assert!(authorize(&rules, &request(3, &[6], &[10])).is_err());
+    }
+
+    #[test]
+    fn allowed_category_covers_its_channels() {
+        let rules = AccessRules {
+            allow: list(&[], &[], &[100]),
+            deny: list(&[], &[], &[11]),
+            ..Default::default()
+        };
+        assert!(authorize(&rules, &request(1, &[], &[10, 100])).is_ok());
+        assert!(authorize(&rules, &request(1, &[], &[11, 100])).is_err());
+        assert!(authorize(&rules, &request(1, &[], &[12])).is_err());
+    }
+
+    #[test]
+    fn admins_bypass_rules() {
+        let rules = AccessRules {
+            deny: list(&[1], &[], &[]),
+            admin_roles: vec![7],
+            ..Default::default()
+        };
+        let mut admin = request(1, &[7], &[10]);
+        admin.is_admin = is_bot_admin(&rules, false, &[7]);
+        assert!(authorize(&rules, &admin).is_ok());
+        assert!(!is_bot_admin(&rules, false, &[8]));
+        assert!(is_bot_admin(&rules, true, &[]));
+    }
+
+    #[test]
+    fn unknown_roles_are_denied_only_when_rules_use_roles() {
+        let unknown = AccessRequest {
+            role_ids: None,
+            ..request(1, &[], &[10])
+        };
+        assert!(authorize(&AccessRules::default(), &unknown).is_ok());
+
+        let deny_role = AccessRules {
+            deny: list(&[], &[5], &[]),
+            ..Default::default()
+        };
+        assert!(authorize(&deny_role, &unknown).is_err());
+
+        let allow_role = AccessRules {
+            allow: list(&[], &[5], &[]),
+            ..Default::default()
+        };
+        assert!(authorize(&allow_role, &unknown).is_err());
+    }
+}
@@ -1,123 +1,533 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::Instrument;
 use uuid::Uuid;
 
 use crate::{
-    application::traits::{
-        ai_client::AIClient, long_term_store::LongTermStore, short_term_store::ShortTermStore,
+    application::{
+        chat::{
+            promotion_service::{enqueue_failed, promote},
+            prompt_envelope::{message_text, wrap_memories},
+        },
+        traits::{
+            ai_client::AIClient, long_term_store::LongTermStore, promotion_queue::PromotionQueue,
+            short_term_store::ShortTermStore,
+        },
     },
     models::{error::AppError, memory::*},
+    shared::metrics::metrics,
 };
 
+/// 1件のユーザー発言に対する応答依頼
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    /// 短期記憶のキー。DMチャンネルはユーザーごとに別なので、DMの会話はユーザー単位で分かれる
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub user_message: String,
+    pub scope: MemoryScope,
+    /// 発言の元になったメッセージのID。短期記憶に同じターンがあれば、追記せず置き換える
+    pub turn_id: u64,
+}
+
+/// 生成した応答と、その生成に使った記憶
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatResponse {
+    pub content: String,
+    pub memories: Vec<String>,
+    pub usage: TokenUsage,
+    /// モデルに送った履歴と、最後に今回の発言。REPLの `/context` で表示する
+    pub context: Vec<ChatMessage>,
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        channel_id = request.channel_id,
+        user_id = request.user_id,
+        guild_id = request.scope.guild_id(),
+        turn_id = request.turn_id,
+    )
+)]
 pub async fn process_message(
     ai_client: &dyn AIClient,
     short_term_store: &dyn ShortTermStore,
     long_term_store: &dyn LongTermStore,
-    channel_id: u64,
-    user_id: u64,
-    user_message: String,
-) -> Result<String, AppError> {
-    let in_memory_context = short_term_store.get_context(channel_id).await;
-
-    let query_embedding = ai_client
-        .embed(user_message.clone())
-        .await
-        .map_err(|e| AppError::Embedding(e.to_string()))?;
-
-    let midterm_results = long_term_store
-        .search_midterm(query_embedding.clone(), user_id, 3)
-        .await
-        .map_err(|e| AppError::Store(e.to_string()))?;
-
-    let longterm_results = long_term_store
-        .search_longterm(query_embedding.clone(), user_id, 5)
-        .await
-        .map_err(|e| AppError::Store(e.to_string()))?;
-
-    let (prompt_message, chat_history) = build_messages(
-        &user_message,
-        &in_memory_context,
-        &midterm_results,
-        &longterm_results,
-    );
+    promotion_queue: &dyn PromotionQueue,
+    request: ChatRequest,
+) -> Result<ChatResponse, AppError> {
+    record_outcome(
+        answer(
+            ai_client,
+            short_term_store,
+            long_term_store,
+            promotion_queue,
+            request,
+            None,
+        )
+        .await,
+    )
+}
 
-    tracing::debug!("Sending {} messages in chat history", chat_history.len());
+/// `process_message` と同じだが、生成中の応答を少しずつ `deltas` に送る
+#[tracing::instrument(
+    skip_all,
+    fields(
+        channel_id = request.channel_id,
+        user_id = request.user_id,
+        guild_id = request.scope.guild_id(),
+        turn_id = request.turn_id,
+    )
+)]
+pub async fn process_message_streaming(
+    ai_client: &dyn AIClient,
+    short_term_store: &dyn ShortTermStore,
+    long_term_store: &dyn LongTermStore,
+    promotion_queue: &dyn PromotionQueue,
+    request: ChatRequest,
+    deltas: UnboundedSender<String>,
+) -> Result<ChatResponse, AppError> {
+    record_outcome(
+        answer(
+            ai_client,
+            short_term_store,
+            long_term_store,
+            promotion_queue,
+            request,
+            Some(deltas),
+        )
+        .await,
+    )
+}
 
-    let response = ai_client
-        .generate(prompt_message, chat_history)
-        .await
-        .map_err(|e| AppError::AIGeneration(e.to_string()))?;
+async fn answer(
+    ai_client: &dyn AIClient,
+    short_term_store: &dyn ShortTermStore,
+    long_term_store: &dyn LongTermStore,
+    promotion_queue: &dyn PromotionQueue,
+    request: ChatRequest,
+    deltas: Option<UnboundedSender<String>>,
+) -> Result<ChatResponse, AppError> {
+    let AssembledContext {
+        regenerating,
+        prompt,
+        history,
+        midterm: midterm_results,
+        longterm: longterm_results,
+    } = assemble(ai_client, short_term_store, long_term_store, &request).await;
+    let ChatRequest {
+        channel_id,
+        user_id,
+        user_message,
+        scope,
+        turn_id,
+    } = request;
+
+    tracing::debug!("Sending {} messages in chat history", history.len());
+    let context = history
+        .iter()
+        .chain(std::iter::once(&prompt))
+        .cloned()
+        .collect();
+
+    let Completion {
+        content: response,
+        usage,
+    } = generate(ai_client, prompt, history, deltas).await?;
 
-    let now = current_timestamp();
     let user_msg = ShortTermMessage {
         role: Role::User,
         user_id,
         content: user_message,
-        timestamp: now,
+        timestamp: current_timestamp(),
+        turn_id,
     };
-    let overflow = short_term_store.push(channel_id, user_msg).await;
-    let fail_count =
-        promote_overflow(ai_client, long_term_store, user_id, channel_id, overflow).await;
-    if fail_count > 0 {
-        tracing::warn!(
-            "promote_overflow: {fail_count} user message(s) failed to promote to midterm memory"
-        );
-    }
-
     let assistant_msg = ShortTermMessage {
         role: Role::Assistant,
         user_id,
         content: response.clone(),
         timestamp: current_timestamp(),
+        turn_id,
+    };
+
+    let overflow = if regenerating {
+        short_term_store
+            .replace_turn(
+                channel_id,
+                turn_id,
+                vec![user_msg.clone(), assistant_msg.clone()],
+            )
+            .await
+    } else {
+        None
     };
-    let overflow = short_term_store.push(channel_id, assistant_msg).await;
-    let fail_count =
-        promote_overflow(ai_client, long_term_store, user_id, channel_id, overflow).await;
+
+    let overflow = match overflow {
+        Some(overflow) => overflow,
+        // 生成中に古いターンが押し出された場合も含め、新しいターンとして追記する
+        None => {
+            let mut overflow = short_term_store.push(channel_id, user_msg).await;
+            overflow.extend(short_term_store.push(channel_id, assistant_msg).await);
+            overflow
+        }
+    };
+
+    let fail_count = promote_overflow(
+        ai_client,
+        long_term_store,
+        promotion_queue,
+        user_id,
+        channel_id,
+        scope,
+        overflow,
+    )
+    .await;
     if fail_count > 0 {
         tracing::warn!(
-            "promote_overflow: {fail_count} assistant message(s) failed to promote to midterm memory"
+            "promote_overflow: {fail_count} message(s) failed to promote to midterm memory"
         );
     }
 
-    Ok(response)
+    Ok(ChatResponse {
+        content: response,
+        memories: memory_texts(&midterm_results, &longterm_results),
+        usage,
+        context,
+    })
+}
+
+/// 依頼に対してモデルに送る内容
+struct AssembledContext {
+    /// 短期記憶に同じターンがあり、答え直す場合
+    regenerating: bool,
+    prompt: ChatMessage,
+    history: Vec<ChatMessage>,
+    midterm: Vec<MidTermMemory>,
+    longterm: Vec<LongTermMemory>,
+}
+
+async fn assemble(
+    ai_client: &dyn AIClient,
+    short_term_store: &dyn ShortTermStore,
+    long_term_store: &dyn LongTermStore,
+    request: &ChatRequest,
+) -> AssembledContext {
+    let in_memory_context = short_term_store.get_context(request.channel_id).await;
+    // 再生成する場合は、そのターンより前の会話だけを踏まえて答え直す
+    let regenerating = in_memory_context
+        .iter()
+        .any(|m| m.turn_id == request.turn_id);
+    let in_memory_context: Vec<ShortTermMessage> = in_memory_context
+        .into_iter()
+        .take_while(|m| m.turn_id != request.turn_id)
+        .collect();
+
+    let (midterm, longterm) = retrieve_memories(
+        ai_client,
+        long_term_store,
+        &request.user_message,
+        request.user_id,
+        request.scope,
+    )
+    .await;
+
+    let (prompt, history) = build_messages(
+        &request.user_message,
+        &in_memory_context,
+        &midterm,
+        &longterm,
+    );
+
+    AssembledContext {
+        regenerating,
+        prompt,
+        history,
+        midterm,
+        longterm,
+    }
+}
+
+/// 短期記憶上の1ターン（ユーザーの発言とその応答）
+#[derive(Debug, Clone, Copy)]
+pub struct TurnRef {
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub scope: MemoryScope,
+    pub turn_id: u64,
+}
+
+/// 応答の続きを促す指示。ユーザーの発言としては記録しない
+const CONTINUE_PROMPT: &str =
+    "Continue your previous answer exactly where it stopped. Do not repeat what you already wrote.";
+
+/// 同じ発言と、それより前の会話から応答を作り直し、ターンの応答を置き換える。
+/// ターンが短期記憶から押し出されていれば、応答した時の発言 `tracked_prompt` から作り直し、新しいターンとして記録する
+pub async fn regenerate_turn(
+    ai_client: &dyn AIClient,
+    short_term_store: &dyn ShortTermStore,
+    long_term_store: &dyn LongTermStore,
+    promotion_queue: &dyn PromotionQueue,
+    turn: TurnRef,
+    tracked_prompt: String,
+) -> Result<ChatResponse, AppError> {
+    let user_message = short_term_store
+        .get_context(turn.channel_id)
+        .await
+        .into_iter()
+        .find(|m| m.turn_id == turn.turn_id && m.role == Role::User)
+        .map_or(tracked_prompt, |m| m.content);
+
+    process_message(
+        ai_client,
+        short_term_store,
+        long_term_store,
+        promotion_queue,
+        ChatRequest {
+            channel_id: turn.channel_id,
+            user_id: turn.user_id,
+            user_message,
+            scope: turn.scope,
+            turn_id: turn.turn_id,
+        },
+    )
+    .await
+}
+
+/// 途中で切れた応答の続きを生成し、ターンの応答に繋げる。続きの部分だけを返す
+#[tracing::instrument(
+    skip_all,
+    fields(
+        channel_id = turn.channel_id,
+        user_id = turn.user_id,
+        guild_id = turn.scope.guild_id(),
+        turn_id = turn.turn_id,
+    )
+)]
+pub async fn continue_turn(
+    ai_client: &dyn AIClient,
+    short_term_store: &dyn ShortTermStore,
+    long_term_store: &dyn LongTermStore,
+    turn: TurnRef,
+) -> Result<String, AppError> {
+    record_outcome(continue_answer(ai_client, short_term_store, long_term_store, turn).await)
+}
+
+async fn continue_answer(
+    ai_client: &dyn AIClient,
+    short_term_store: &dyn ShortTermStore,
+    long_term_store: &dyn LongTermStore,
+    turn: TurnRef,
+) -> Result<String, AppError> {
+    let context = short_term_store.get_context(turn.channel_id).await;
+    let Some(last_index) = context.iter().rposition(|m| m.turn_id == turn.turn_id) else {
+        return Err(AppError::ConversationNotFound(turn.channel_id));
+    };
+    let history = &context[..= last_index];
+    let turn_messages: Vec<ShortTermMessage> = history
+        .iter()
+        .filter(|m| m.turn_id == turn.turn_id)
+        .cloned()
+        .collect();
+    let Some(user_message) = turn_messages.iter().find(|m| m.role == Role::User) else {
+        return Err(AppError::ConversationNotFound(turn.channel_id));
+    };
+
+    let (midterm_results, longterm_results) = retrieve_memories(
+        ai_client,
+        long_term_store,
+        &user_message.content,
+        turn.user_id,
+        turn.scope,
+    )
+    .await;
+
+    let (prompt_message, chat_history) = build_messages(
+        CONTINUE_PROMPT,
+        history,
+        &midterm_results,
+        &longterm_results,
+    );
+
+    let continuation = generate(ai_client, prompt_message, chat_history, None)
+        .await?
+        .content;
+
+    let extended = extend_assistant_message(turn_messages, &continuation);
+    // 生成中に押し出されていた場合は、続きを記録しない
+    short_term_store
+        .replace_turn(turn.channel_id, turn.turn_id, extended)
+        .await;
+
+    Ok(continuation)
+}
+
+/// 応答と、その元になった短期記憶・中期記憶を忘れる
+pub async fn delete_turn(
+    short_term_store: &dyn ShortTermStore,
+    long_term_store: &dyn LongTermStore,
+    turn: TurnRef,
+) -> Result<(), AppError> {
+    short_term_store
+        .remove_turn(turn.channel_id, turn.turn_id)
+        .await;
+
+    long_term_store
+        .delete_midterm_turn(turn.user_id, turn.turn_id)
+        .await
+        .map_err(|e| AppError::Store(e.to_string()))
+}
+
+fn record_outcome<T>(result: Result<T, AppError>) -> Result<T, AppError> {
+    metrics().record_request(result.is_ok());
+    if let Err(err) = &result {
+        metrics().record_error(err.kind());
+    }
+    result
+}
+
+async fn generate(
+    ai_client: &dyn AIClient,
+    prompt: ChatMessage,
+    chat_history: Vec<ChatMessage>,
+    deltas: Option<UnboundedSender<String>>,
+) -> Result<Completion, AppError> {
+    let timer = metrics().stage_timer("generate");
+    let completion = match deltas {
+        Some(deltas) => {
+            ai_client
+                .generate_streaming(prompt, chat_history, deltas)
+                .instrument(tracing::info_span!("generate"))
+                .await
+        }
+        None => {
+            ai_client
+                .generate(prompt, chat_history)
+                .instrument(tracing::info_span!("generate"))
+                .await
+        }
+    }
+    .map_err(|e| AppError::AIGeneration(e.to_string()))?;
+    timer.observe_duration();
+
+    metrics().record_tokens(
+        completion.usage.input_tokens,
+        completion.usage.output_tokens,
+    );
+    Ok(completion)
+}
+
+fn extend_assistant_message(
+    mut turn_messages: Vec<ShortTermMessage>,
+    continuation: &str,
+) -> Vec<ShortTermMessage> {
+    if let Some(assistant) = turn_messages
+        .iter_mut()
+        .rev()
+        .find(|m| m.role == Role::Assistant)
+    {
+        assistant.content.push_str(continuation);
+        assistant.timestamp = current_timestamp();
+    }
+    turn_messages
+}
+
+fn memory_texts(midterm: &[MidTermMemory], longterm: &[LongTermMemory]) -> Vec<String> {
+    longterm
+        .iter()
+        .map(|m| m.fact.clone())
+        .chain(midterm.iter().map(|m| m.summary.clone()))
+        .collect()
+}
+
+/// 記憶の検索に失敗しても応答は止めず、短期記憶のみで回答できるよう空の結果を返す
+async fn retrieve_memories(
+    ai_client: &dyn AIClient,
+    long_term_store: &dyn LongTermStore,
+    user_message: &str,
+    user_id: u64,
+    scope: MemoryScope,
+) -> (Vec<MidTermMemory>, Vec<LongTermMemory>) {
+    let result = async {
+        let timer = metrics().stage_timer("embed");
+        let query_embedding = ai_client
+            .embed(user_message.to_string())
+            .instrument(tracing::info_span!("embed"))
+            .await
+            .map_err(|e| AppError::Embedding(e.to_string()))?;
+        timer.observe_duration();
+
+        let timer = metrics().stage_timer("search");
+        let (midterm_results, longterm_results) = async {
+            let midterm_results = long_term_store
+                .search_midterm(query_embedding.clone(), user_id, scope, 3)
+                .await
+                .map_err(|e| AppError::Store(e.to_string()))?;
+
+            let longterm_results = long_term_store
+                .search_longterm(query_embedding, user_id, scope, 5)
+                .await
+                .map_err(|e| AppError::Store(e.to_string()))?;
+
+            Ok::<_, AppError>((midterm_results, longterm_results))
+        }
+        .instrument(tracing::info_span!("search"))
+        .await?;
+        timer.observe_duration();
+
+        Ok::<_, AppError>((midterm_results, longterm_results))
+    }
+    .await;
+
+    match result {
+        Ok(memories) => memories,
+        Err(err) => {
+            metrics().record_error(err.kind());
+            tracing::warn!(
+                user_id,
+                error = %err,
+                "Memory unavailable; answering with short-term context only"
+            );
+            (Vec::new(), Vec::new())
+        }
+    }
 }
 
+#[tracing::instrument(skip_all, fields(messages = overflow.len()))]
 async fn promote_overflow(
     ai_client: &dyn AIClient,
     long_term_store: &dyn LongTermStore,
+    promotion_queue: &dyn PromotionQueue,
     user_id: u64,
     channel_id: u64,
+    scope: MemoryScope,
     overflow: Vec<ShortTermMessage>,
 ) -> usize {
     let mut fail_count = 0usize;
 
     for msg in overflow {
-        let role_str = match msg.role {
-            Role::User => "user",
-            Role::Assistant => "assistant",
-        };
-        let summary = format!("[{}] {}", role_str, msg.content);
-        let embedding = match ai_client.embed(summary.clone()).await {
-            Ok(e) => e,
-            Err(err) => {
-                tracing::warn!("Failed to embed overflow message for midterm: {err}");
-                fail_count += 1;
-                continue;
-            }
+        // 発言は包まれたエスケープ済みの形で短期記憶にあるので、本文だけを残す
+        let (role_str, content) = match msg.role {
+            Role::User => ("user", message_text(&msg.content)),
+            Role::Assistant => ("assistant", msg.content),
         };
-
         let now = current_timestamp();
         let memory = MidTermMemory {
             id: Uuid::new_v4().to_string(),
             user_id,
             channel_id,
-            summary,
+            summary: format!("[{role_str}] {content}"),
             created_at: now,
             expires_at: now + 60 * 60 * 24 * 7, // 7日
+            direct_message: scope == MemoryScope::DirectMessage,
+            guild_id: scope.guild_id(),
+            turn_id: msg.turn_id,
         };
 
-        if let Err(err) = long_term_store.store_midterm(memory, embedding).await {
-            tracing::warn!("Failed to store midterm memory: {err}");
+        if let Err(err) = promote(ai_client, long_term_store, &memory).await {
+            tracing::warn!("Failed to promote overflow message to midterm: {err}");
+            metrics().record_promotion_failure(false);
+            enqueue_failed(promotion_queue, memory, &err, now).await;
             fail_count += 1;
         }
     }
@@ -133,25 +543,8 @@ fn build_messages(
 ) -> (ChatMessage, Vec<ChatMessage>) {
     let mut history: Vec<ChatMessage> = Vec::new();
 
-    if !longterm.is_empty() || !midterm.is_empty() {
-        let mut context_text = String::new();
-
-        if !longterm.is_empty() {
-            context_text.push_str("[What we know about this user]\n");
-            for point in longterm {
-                context_text.push_str(&format!("- {}\n", point.fact));
-            }
-            context_text.push('\n');
-        }
-
-        if !midterm.is_empty() {
-            context_text.push_str("[Summarizing relevant past conversations]\n");
-            for point in midterm {
-                context_text.push_str(&format!("- {}\n", point.summary));
-            }
-        }
-
-        history.push(ChatMessage::user(context_text.trim_end().to_string()));
+    if let Some(context_text) = wrap_memories(longterm, midterm) {
+        history.push(ChatMessage::user(context_text));
         history.push(ChatMessage::assistant(
             "Understood. I will use this context in our conversation.",
         ));
@@ -170,7 +563,7 @@ fn build_messages(
     (prompt, history)
 }
 
-fn current_timestamp() -> i64 {
+pub(crate) fn current_timestamp() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -180,6 +573,65 @@ fn current_timestamp() -> i64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        infrastructure::store::in_memory_store::InMemoryStore,
+        test_support::{FakeAI, FakeQueue, FakeStore},
+    };
+
+    #[tokio::test]
+    async fn regenerating_a_turn_gone_from_short_term_memory_uses_the_tracked_prompt() {
+        let short_term_store = InMemoryStore::new(10);
+        let turn = TurnRef {
+            channel_id: 10,
+            user_id: 1,
+            scope: MemoryScope::DirectMessage,
+            turn_id: 5,
+        };
+
+        let response = regenerate_turn(
+            &FakeAI::replying(&["again"]),
+            &short_term_store,
+            &FakeStore::default(),
+            &FakeQueue::default(),
+            turn,
+            "<message>hi</message>".to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.content, "again");
+        assert_eq!(
+            response.context.last(),
+            Some(&ChatMessage::user("<message>hi</message>"))
+        );
+        let context = short_term_store.get_context(10).await;
+        assert_eq!(context.len(), 2);
+        assert!(context.iter().all(|m| m.turn_id == 5));
+    }
+
+    #[test]
+    fn continuation_is_appended_to_assistant_message() {
+        let turn = vec![
+            ShortTermMessage {
+                role: Role::User,
+                user_id: 1,
+                content: "write a poem".to_string(),
+                timestamp: 0,
+                turn_id: 1,
+            },
+            ShortTermMessage {
+                role: Role::Assistant,
+                user_id: 1,
+                content: "Roses are red,".to_string(),
+                timestamp: 0,
+                turn_id: 1,
+            },
+        ];
+
+        let extended = extend_assistant_message(turn, " violets are blue.");
+        assert_eq!(extended[0].content, "write a poem");
+        assert_eq!(extended[1].content, "Roses are red, violets are blue.");
+    }
 
     #[test]
     fn build_messages_no_context() {
@@ -196,12 +648,14 @@ mod tests {
                 user_id: 1,
                 content: "hi".to_string(),
                 timestamp: 0,
+                turn_id: 1,
             },
             ShortTermMessage {
                 role: Role::Assistant,
                 user_id: 1,
                 content: "hello".to_string(),
                 timestamp: 1,
+                turn_id: 1,
             },
         ];
 
@@ -219,6 +673,7 @@ mod tests {
             category: "preference".to_string(),
             created_at: 0,
             updated_at: 0,
+            guild_id: Some(1),
         }];
 
         let (_prompt, history) = build_messages("hello", &[], &[], &longterm);
@@ -235,6 +690,9 @@ mod tests {
             summary: "Discussed project".to_string(),
             created_at: 0,
             expires_at: 999,
+            direct_message: false,
+            guild_id: Some(1),
+            turn_id: 0,
         }];
 
         let (_prompt, history) = build_messages("hello", &[], &midterm, &[]);
@@ -248,6 +706,7 @@ mod tests {
             user_id: 1,
             content: "prev msg".to_string(),
             timestamp: 0,
+            turn_id: 1,
         }];
         let midterm = vec![MidTermMemory {
             id: "1".to_string(),
@@ -256,6 +715,9 @@ mod tests {
             summary: "Past talk".to_string(),
             created_at: 0,
             expires_at: 999,
+            direct_message: false,
+            guild_id: Some(1),
+            turn_id: 0,
         }];
         let longterm = vec![LongTermMemory {
             id: "1".to_string(),
@@ -264,6 +726,7 @@ mod tests {
             category: "preference".to_string(),
             created_at: 0,
             updated_at: 0,
+            guild_id: Some(1),
         }];
 
         let (prompt, history) = build_messages("new msg", &short, &midterm, &longterm);
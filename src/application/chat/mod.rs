@@ -1 +1,3 @@
 pub mod chat_service;
+pub mod promotion_service;
+pub mod prompt_envelope;
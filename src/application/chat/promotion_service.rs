@@ -0,0 +1,223 @@
+use anyhow::Result;
+
+use crate::{
+    application::traits::{
+        ai_client::AIClient, long_term_store::LongTermStore, promotion_queue::PromotionQueue,
+    },
+    models::{
+        error::CircuitOpen,
+        memory::{MidTermMemory, PendingPromotion},
+    },
+    shared::{config::PromotionRetry, metrics::metrics},
+};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RetryReport {
+    pub succeeded: usize,
+    pub rescheduled: usize,
+    pub dropped: usize,
+}
+
+pub async fn promote(
+    ai_client: &dyn AIClient,
+    long_term_store: &dyn LongTermStore,
+    memory: &MidTermMemory,
+) -> Result<()> {
+    let embedding = ai_client.embed(memory.summary.clone()).await?;
+    long_term_store
+        .store_midterm(memory.clone(), embedding)
+        .await
+}
+
+/// 昇格に失敗した記憶をリトライキューに積む。最初のリトライは次回のキュー処理で行われる
+pub async fn enqueue_failed(
+    promotion_queue: &dyn PromotionQueue,
+    memory: MidTermMemory,
+    error: &anyhow::Error,
+    now: i64,
+) -> bool {
+    let memory_id = memory.id.clone();
+    let pending = PendingPromotion {
+        memory,
+        attempts: 1,
+        next_attempt_at: now,
+        last_error: error.to_string(),
+    };
+
+    match promotion_queue.enqueue(pending).await {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::error!(
+                memory_id,
+                "Failed to enqueue midterm promotion for retry: {err}"
+            );
+            false
+        }
+    }
+}
+
+pub async fn retry_due_promotions(
+    ai_client: &dyn AIClient,
+    long_term_store: &dyn LongTermStore,
+    promotion_queue: &dyn PromotionQueue,
+    retry: &PromotionRetry,
+    now: i64,
+) -> RetryReport {
+    let mut report = RetryReport::default();
+
+    for mut pending in promotion_queue.due(now).await {
+        let memory_id = pending.memory.id.clone();
+
+        let expired = pending.memory.expires_at <= now;
+        let outcome = if expired {
+            Err(anyhow::anyhow!(
+                "memory expired before it could be promoted"
+            ))
+        } else {
+            promote(ai_client, long_term_store, &pending.memory).await
+        };
+
+        let result = match outcome {
+            Ok(()) => {
+                report.succeeded += 1;
+                promotion_queue.remove(&memory_id).await
+            }
+            Err(err) if err.is::<CircuitOpen>() => {
+                // 依存先の停止中は試行回数を消費せずに待つ
+                pending.next_attempt_at = now + backoff_secs(retry, pending.attempts);
+                report.rescheduled += 1;
+                promotion_queue.enqueue(pending).await
+            }
+            Err(err) if expired || pending.attempts >= retry.max_attempts => {
+                tracing::error!(
+                    memory_id,
+                    attempts = pending.attempts,
+                    "Giving up on midterm promotion: {err}"
+                );
+                metrics().record_promotion_failure(true);
+                report.dropped += 1;
+                promotion_queue.remove(&memory_id).await
+            }
+            Err(err) => {
+                pending.attempts += 1;
+                pending.next_attempt_at = now + backoff_secs(retry, pending.attempts);
+                pending.last_error = err.to_string();
+                report.rescheduled += 1;
+                promotion_queue.enqueue(pending).await
+            }
+        };
+
+        if let Err(err) = result {
+            tracing::error!(memory_id, "Failed to update promotion retry queue: {err}");
+        }
+    }
+
+    metrics()
+        .promotion_queue_depth
+        .set(promotion_queue.depth().await as i64);
+
+    report
+}
+
+fn backoff_secs(retry: &PromotionRetry, attempts: u32) -> i64 {
+    let exponent = attempts.saturating_sub(1).min(32);
+    let delay = retry.base_backoff_secs.saturating_mul(1u64 << exponent);
+    delay.min(retry.max_backoff_secs) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{FakeAI, FakeQueue, FakeStore};
+
+    fn make_pending(attempts: u32) -> PendingPromotion {
+        PendingPromotion {
+            memory: MidTermMemory {
+                id: "m1".to_string(),
+                user_id: 1,
+                channel_id: 100,
+                summary: "[user] hello".to_string(),
+                created_at: 0,
+                expires_at: 10_000,
+                direct_message: false,
+                guild_id: Some(1),
+                turn_id: 0,
+            },
+            attempts,
+            next_attempt_at: 0,
+            last_error: String::new(),
+        }
+    }
+
+    fn retry_config() -> PromotionRetry {
+        PromotionRetry {
+            max_attempts: 3,
+            base_backoff_secs: 10,
+            max_backoff_secs: 25,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_and_is_capped() {
+        let retry = retry_config();
+        assert_eq!(backoff_secs(&retry, 1), 10);
+        assert_eq!(backoff_secs(&retry, 2), 20);
+        assert_eq!(backoff_secs(&retry, 3), 25);
+        assert_eq!(backoff_secs(&retry, 100), 25);
+    }
+
+    #[tokio::test]
+    async fn successful_retry_removes_entry() {
+        let store = FakeStore::default();
+        let queue = FakeQueue::default();
+        queue.enqueue(make_pending(1)).await.unwrap();
+
+        let report =
+            retry_due_promotions(&FakeAI::default(), &store, &queue, &retry_config(), 100).await;
+
+        assert_eq!(report.succeeded, 1);
+        assert_eq!(store.events(), ["midterm m1"]);
+        assert_eq!(queue.depth().await, 0);
+    }
+
+    #[tokio::test]
+    async fn failed_retry_is_rescheduled_with_backoff() {
+        let queue = FakeQueue::default();
+        queue.enqueue(make_pending(1)).await.unwrap();
+
+        let report = retry_due_promotions(
+            &FakeAI::failing_embed(),
+            &FakeStore::default(),
+            &queue,
+            &retry_config(),
+            100,
+        )
+        .await;
+
+        assert_eq!(report.rescheduled, 1);
+        let entries = queue.entries.lock().unwrap();
+        assert_eq!(entries[0].attempts, 2);
+        assert_eq!(entries[0].next_attempt_at, 120);
+    }
+
+    #[tokio::test]
+    async fn exhausted_retry_is_dropped_and_counted() {
+        let queue = FakeQueue::default();
+        queue.enqueue(make_pending(3)).await.unwrap();
+        let permanent_failures = metrics().promotion_failures(true);
+
+        let report = retry_due_promotions(
+            &FakeAI::failing_embed(),
+            &FakeStore::default(),
+            &queue,
+            &retry_config(),
+            100,
+        )
+        .await;
+
+        assert_eq!(report.dropped, 1);
+        assert_eq!(metrics().promotion_failures(true), permanent_failures + 1);
+        assert_eq!(queue.depth().await, 0);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::models::feedback::{Feedback, Rating};
+
+/// モデルとシステムプロンプトの組ごとの評価の集計
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedbackSummary {
+    pub model: String,
+    pub instruction_version: String,
+    pub good: usize,
+    pub bad: usize,
+    pub first_rated_at: i64,
+    pub last_rated_at: i64,
+}
+
+impl FeedbackSummary {
+    /// 👍の割合（0.0〜1.0）
+    pub fn approval_rate(&self) -> f64 {
+        let total = self.good + self.bad;
+        if total == 0 {
+            0.0
+        } else {
+            self.good as f64 / total as f64
+        }
+    }
+}
+
+/// モデルとシステムプロンプトの組ごとに評価を集計し、新しく評価されたものから並べる
+pub fn summarize(feedback: &[Feedback]) -> Vec<FeedbackSummary> {
+    let mut groups: HashMap<(&str, &str), FeedbackSummary> = HashMap::new();
+
+    for f in feedback {
+        let summary = groups
+            .entry((f.model.as_str(), f.instruction_version.as_str()))
+            .or_insert_with(|| FeedbackSummary {
+                model: f.model.clone(),
+                instruction_version: f.instruction_version.clone(),
+                good: 0,
+                bad: 0,
+                first_rated_at: f.rated_at,
+                last_rated_at: f.rated_at,
+            });
+
+        match f.rating {
+            Rating::Good => summary.good += 1,
+            Rating::Bad => summary.bad += 1,
+        }
+        summary.first_rated_at = summary.first_rated_at.min(f.rated_at);
+        summary.last_rated_at = summary.last_rated_at.max(f.rated_at);
+    }
+
+    let mut summaries: Vec<FeedbackSummary> = groups.into_values().collect();
+    summaries.sort_by_key(|s| Reverse(s.last_rated_at));
+    summaries
+}
+
+/// システムプロンプトの内容から短い版の識別子を作る。内容が変われば別の版として集計される
+pub fn instruction_version(instruction: &str) -> String {
+    // FNV-1a。Rustのバージョンに依存せず、再起動しても同じ値になる
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in instruction.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:08x}", hash >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feedback(model: &str, version: &str, rating: Rating, rated_at: i64) -> Feedback {
+        Feedback {
+            turn_id: rated_at as u64,
+            guild_id: Some(1),
+            channel_id: 10,
+            requester_id: 2,
+            rater_id: 3,
+            rating,
+            prompt: String::new(),
+            memories: Vec::new(),
+            model: model.to_string(),
+            instruction_version: version.to_string(),
+            response: String::new(),
+            rated_at,
+        }
+    }
+
+    #[test]
+    fn groups_by_model_and_instruction() {
+        let summaries = summarize(&[
+            feedback("a", "v1", Rating::Good, 10),
+            feedback("a", "v1", Rating::Bad, 20),
+            feedback("a", "v1", Rating::Good, 5),
+            feedback("a", "v2", Rating::Good, 30),
+            feedback("b", "v1", Rating::Bad, 15),
+        ]);
+
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[0].instruction_version, "v2");
+
+        let a_v1 = summaries
+            .iter()
+            .find(|s| s.model == "a" && s.instruction_version == "v1")
+            .unwrap();
+        assert_eq!((a_v1.good, a_v1.bad), (2, 1));
+        assert_eq!((a_v1.first_rated_at, a_v1.last_rated_at), (5, 20));
+        assert!((a_v1.approval_rate() - 2.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn instruction_version_is_stable() {
+        assert_eq!(instruction_version(""), "cbf29ce4");
+        assert_ne!(instruction_version("a"), instruction_version("b"));
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use tokio::sync::Mutex;
+
+use crate::{
+    application::traits::{
+        ai_client::AIClient, long_term_store::LongTermStore, short_term_store::ShortTermStore,
+    },
+    models::health::{CheckResult, HealthReport},
+};
+
+/// 1つの依存先の確認を待つ最大時間
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Qdrant・生成API・埋め込みAPIの確認結果を `ttl` の間使い回す。
+/// 埋め込みAPIの確認は課金されるので、`/readyz` を頻繁に叩かれても呼び出しは `ttl` ごとに1回にする
+pub struct HealthChecker {
+    ai_client: Arc<dyn AIClient>,
+    long_term_store: Arc<dyn LongTermStore>,
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, Vec<CheckResult>)>>,
+}
+
+impl HealthChecker {
+    /// ブレーカーが開いていても依存先そのものの状態を確かめるため、サーキットブレーカーを通さないクライアントを渡す
+    pub fn new(
+        ai_client: Arc<dyn AIClient>,
+        long_term_store: Arc<dyn LongTermStore>,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            ai_client,
+            long_term_store,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub async fn check(
+        &self,
+        short_term_store: &dyn ShortTermStore,
+        uptime: Duration,
+    ) -> HealthReport {
+        let (checks, short_term) = tokio::join!(self.dependencies(), short_term_store.stats());
+        HealthReport {
+            checks,
+            short_term,
+            uptime,
+        }
+    }
+
+    /// 確認中はロックを持ったままにして、同時に来たプローブが同じ結果を待つようにする
+    async fn dependencies(&self) -> Vec<CheckResult> {
+        let mut cached = self.cached.lock().await;
+        if let Some((checked_at, checks)) = cached.as_ref()
+            && checked_at.elapsed() < self.ttl
+        {
+            return checks.clone();
+        }
+
+        let checks =
+            check_dependencies(self.ai_client.as_ref(), self.long_term_store.as_ref()).await;
+        *cached = Some((Instant::now(), checks.clone()));
+        checks
+    }
+}
+
+/// Qdrant・生成API・埋め込みAPIを並行して確認する
+async fn check_dependencies(
+    ai_client: &dyn AIClient,
+    long_term_store: &dyn LongTermStore,
+) -> Vec<CheckResult> {
+    let (qdrant, generation, embedding) = tokio::join!(
+        timed("qdrant", long_term_store.collection_stats(), |stats| {
+            stats
+                .iter()
+                .map(|c| format!("{}: {} ({} points)", c.name, c.status, c.points))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }),
+        timed("generation", ai_client.ping_generation(), |_| String::new()),
+        timed("embedding", ai_client.embed("ping".to_string()), |v| {
+            format!("{} dimensions", v.len())
+        }),
+    );
+
+    vec![qdrant, generation, embedding]
+}
+
+async fn timed<T>(
+    name: &'static str,
+    check: impl Future<Output = Result<T>>,
+    describe: impl FnOnce(T) -> String,
+) -> CheckResult {
+    let started = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, check).await;
+    let latency = started.elapsed();
+
+    match result {
+        Ok(Ok(value)) => CheckResult {
+            name,
+            healthy: true,
+            latency,
+            detail: describe(value),
+        },
+        Ok(Err(err)) => CheckResult {
+            name,
+            healthy: false,
+            latency,
+            detail: err.to_string(),
+        },
+        Err(_) => CheckResult {
+            name,
+            healthy: false,
+            latency,
+            detail: format!("timed out after {}s", CHECK_TIMEOUT.as_secs()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        infrastructure::store::in_memory_store::InMemoryStore,
+        models::health::CollectionStats,
+        test_support::{FakeAI, FakeStore},
+    };
+
+    fn store() -> FakeStore {
+        FakeStore {
+            stats: vec![CollectionStats {
+                name: "midterm_memory".to_string(),
+                status: "green".to_string(),
+                points: 3,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn checker(ai_client: Arc<FakeAI>, ttl: Duration) -> HealthChecker {
+        HealthChecker::new(ai_client, Arc::new(store()), ttl)
+    }
+
+    #[tokio::test]
+    async fn reports_every_dependency() {
+        let report = checker(Arc::new(FakeAI::default()), Duration::ZERO)
+            .check(&InMemoryStore::new(10), Duration::from_secs(60))
+            .await;
+
+        assert!(report.is_healthy());
+        let names: Vec<_> = report.checks.iter().map(|c| c.name).collect();
+        assert_eq!(names, ["qdrant", "generation", "embedding"]);
+        assert_eq!(report.checks[0].detail, "midterm_memory: green (3 points)");
+        assert_eq!(report.checks[2].detail, "4 dimensions");
+    }
+
+    #[tokio::test]
+    async fn failing_dependency_makes_report_unhealthy() {
+        let report = checker(Arc::new(FakeAI::failing_embed()), Duration::ZERO)
+            .check(&InMemoryStore::new(10), Duration::ZERO)
+            .await;
+
+        assert!(!report.is_healthy());
+        let embedding = report
+            .checks
+            .iter()
+            .find(|c| c.name == "embedding")
+            .unwrap();
+        assert!(!embedding.healthy);
+        assert_eq!(embedding.detail, "embedding API unavailable");
+    }
+
+    #[tokio::test]
+    async fn dependency_checks_are_reused_within_the_ttl() {
+        let ai_client = Arc::new(FakeAI::default());
+        let short_term_store = InMemoryStore::new(10);
+
+        let cached = checker(ai_client.clone(), Duration::from_secs(60));
+        cached.check(&short_term_store, Duration::ZERO).await;
+        cached.check(&short_term_store, Duration::ZERO).await;
+        assert_eq!(ai_client.embed_calls(), 1);
+
+        let uncached = checker(ai_client.clone(), Duration::ZERO);
+        uncached.check(&short_term_store, Duration::ZERO).await;
+        uncached.check(&short_term_store, Duration::ZERO).await;
+        assert_eq!(ai_client.embed_calls(), 3);
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+
+use crate::{
+    application::traits::{ai_client::AIClient, long_term_store::LongTermStore},
+    models::memory::{LongTermMemory, MemoryExport, MidTermMemory},
+};
+
+/// 保存した記憶の件数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreReport {
+    pub midterm: usize,
+    pub longterm: usize,
+}
+
+/// 埋め込みを作り直して保存する。同じIDの記憶は上書きされる
+pub async fn import_memories(
+    ai_client: &dyn AIClient,
+    long_term_store: &dyn LongTermStore,
+    memories: MemoryExport,
+) -> Result<StoreReport> {
+    let embedded = embed_all(ai_client, memories).await?;
+    store_all(long_term_store, embedded).await
+}
+
+/// 全ての記憶の埋め込みを今の埋め込みモデルで作り直す。
+/// `recreate` ではコレクションを作り直すので、次元数の違うモデルに移行できる。
+/// 作り直す前に `backup` で全件を書き出し、その後の保存に失敗しても取り込み直せるようにする
+pub async fn reembed_memories(
+    ai_client: &dyn AIClient,
+    long_term_store: &dyn LongTermStore,
+    recreate: bool,
+    backup: impl AsyncFnOnce(&MemoryExport) -> Result<()>,
+) -> Result<StoreReport> {
+    let memories = long_term_store
+        .list_memories(None)
+        .await
+        .context("Failed to read memories")?;
+    // コレクションを消す前に全件の埋め込みを作り、途中で失敗しても記憶が失われないようにする
+    let embedded = embed_all(ai_client, memories.clone()).await?;
+
+    if recreate {
+        backup(&memories)
+            .await
+            .context("Failed to back up memories before recreating collections")?;
+        long_term_store
+            .recreate_collections()
+            .await
+            .context("Failed to recreate collections")?;
+    }
+    store_all(long_term_store, embedded).await
+}
+
+struct EmbeddedMemories {
+    midterm: Vec<(MidTermMemory, Vec<f32>)>,
+    longterm: Vec<(LongTermMemory, Vec<f32>)>,
+}
+
+async fn embed_all(ai_client: &dyn AIClient, memories: MemoryExport) -> Result<EmbeddedMemories> {
+    let mut midterm = Vec::with_capacity(memories.midterm.len());
+    for memory in memories.midterm {
+        let embedding = ai_client
+            .embed(memory.summary.clone())
+            .await
+            .with_context(|| format!("Failed to embed midterm memory {}", memory.id))?;
+        midterm.push((memory, embedding));
+    }
+
+    let mut longterm = Vec::with_capacity(memories.longterm.len());
+    for memory in memories.longterm {
+        let embedding = ai_client
+            .embed(memory.fact.clone())
+            .await
+            .with_context(|| format!("Failed to embed longterm memory {}", memory.id))?;
+        longterm.push((memory, embedding));
+    }
+
+    Ok(EmbeddedMemories { midterm, longterm })
+}
+
+async fn store_all(
+    long_term_store: &dyn LongTermStore,
+    embedded: EmbeddedMemories,
+) -> Result<StoreReport> {
+    let mut report = StoreReport::default();
+    for (memory, embedding) in embedded.midterm {
+        let id = memory.id.clone();
+        long_term_store
+            .store_midterm(memory, embedding)
+            .await
+            .with_context(|| format!("Failed to store midterm memory {id}"))?;
+        report.midterm += 1;
+    }
+    for (memory, embedding) in embedded.longterm {
+        let id = memory.id.clone();
+        long_term_store
+            .store_longterm(memory, embedding)
+            .await
+            .with_context(|| format!("Failed to store longterm memory {id}"))?;
+        report.longterm += 1;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{FakeAI, FakeStore};
+
+    fn sample_memories() -> MemoryExport {
+        MemoryExport {
+            midterm: vec![MidTermMemory {
+                id: "m1".to_string(),
+                user_id: 1,
+                channel_id: 2,
+                summary: "summary".to_string(),
+                created_at: 0,
+                expires_at: 100,
+                direct_message: false,
+                guild_id: Some(3),
+                turn_id: 4,
+            }],
+            longterm: vec![LongTermMemory {
+                id: "l1".to_string(),
+                user_id: 1,
+                fact: "fact".to_string(),
+                category: "preference".to_string(),
+                created_at: 0,
+                updated_at: 0,
+                guild_id: Some(3),
+            }],
+        }
+    }
+
+    /// 書き出した記憶のIDを、ストアの操作と同じ列に記録する
+    fn record_backup(store: &FakeStore) -> impl AsyncFnOnce(&MemoryExport) -> Result<()> {
+        async |memories: &MemoryExport| {
+            let ids: Vec<_> = memories
+                .midterm
+                .iter()
+                .map(|m| m.id.as_str())
+                .chain(memories.longterm.iter().map(|l| l.id.as_str()))
+                .collect();
+            store
+                .events
+                .lock()
+                .unwrap()
+                .push(format!("backup {}", ids.join(" ")));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn reembed_recreates_collections_before_storing_everything() {
+        let store = FakeStore {
+            memories: sample_memories(),
+            ..Default::default()
+        };
+
+        let report = reembed_memories(&FakeAI::default(), &store, true, record_backup(&store))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            StoreReport {
+                midterm: 1,
+                longterm: 1
+            }
+        );
+        assert_eq!(
+            store.events(),
+            vec!["backup m1 l1", "recreate", "midterm m1", "longterm l1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_embedding_leaves_collections_untouched() {
+        let store = FakeStore {
+            memories: sample_memories(),
+            ..Default::default()
+        };
+
+        let result = reembed_memories(
+            &FakeAI::failing_embed(),
+            &store,
+            true,
+            record_backup(&store),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(store.events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_store_after_recreate_keeps_the_backup() {
+        let store = FakeStore {
+            memories: sample_memories(),
+            fail_store: true,
+            ..Default::default()
+        };
+
+        let result =
+            reembed_memories(&FakeAI::default(), &store, true, record_backup(&store)).await;
+
+        assert!(result.is_err());
+        assert_eq!(store.events(), vec!["backup m1 l1", "recreate"]);
+    }
+
+    #[tokio::test]
+    async fn failed_backup_leaves_collections_untouched() {
+        let store = FakeStore {
+            memories: sample_memories(),
+            ..Default::default()
+        };
+
+        let result = reembed_memories(
+            &FakeAI::default(),
+            &store,
+            true,
+            async |_: &MemoryExport| anyhow::bail!("disk full"),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(store.events().is_empty());
+    }
+}
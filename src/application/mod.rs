@@ -1,2 +1,6 @@
+pub mod access;
 pub mod chat;
+pub mod feedback;
+pub mod health;
+pub mod maintenance;
 pub mod traits;
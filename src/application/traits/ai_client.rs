@@ -1,11 +1,32 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::models::memory::ChatMessage;
+use crate::models::memory::{ChatMessage, Completion};
 
 #[async_trait]
 pub trait AIClient: Send + Sync {
-    async fn generate(&self, prompt: ChatMessage, chat_history: Vec<ChatMessage>)
-    -> Result<String>;
+    async fn generate(
+        &self,
+        prompt: ChatMessage,
+        chat_history: Vec<ChatMessage>,
+    ) -> Result<Completion>;
+
+    /// Sends text deltas to `deltas` while generating and returns the whole completion.
+    /// Clients that cannot stream send the completion as a single delta.
+    async fn generate_streaming(
+        &self,
+        prompt: ChatMessage,
+        chat_history: Vec<ChatMessage>,
+        deltas: UnboundedSender<String>,
+    ) -> Result<Completion> {
+        let completion = self.generate(prompt, chat_history).await?;
+        let _ = deltas.send(completion.content.clone());
+        Ok(completion)
+    }
+
     async fn embed(&self, text: String) -> Result<Vec<f32>>;
+
+    /// Checks that the generation endpoint is reachable without generating anything.
+    async fn ping_generation(&self) -> Result<()>;
 }
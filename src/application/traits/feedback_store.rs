@@ -0,0 +1,15 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::models::feedback::{Feedback, Rating};
+
+#[async_trait]
+pub trait FeedbackStore: Send + Sync {
+    /// Stores the feedback, replacing an earlier rating by the same rater for the same turn.
+    async fn record(&self, feedback: Feedback) -> Result<()>;
+
+    /// Removes the rater's feedback for the turn if it still has the given rating.
+    async fn retract(&self, turn_id: u64, rater_id: u64, rating: Rating) -> Result<()>;
+
+    async fn list(&self, guild_id: Option<u64>) -> Vec<Feedback>;
+}
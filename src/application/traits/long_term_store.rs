@@ -1,7 +1,10 @@
 use anyhow::Result;
 use async_trait::async_trait;
 
-use crate::models::memory::{LongTermMemory, MidTermMemory};
+use crate::models::{
+    health::CollectionStats,
+    memory::{LongTermMemory, MemoryExport, MemoryScope, MidTermMemory},
+};
 
 #[async_trait]
 pub trait LongTermStore: Send + Sync {
@@ -13,6 +16,7 @@ pub trait LongTermStore: Send + Sync {
         &self,
         embedding: Vec<f32>,
         user_id: u64,
+        scope: MemoryScope,
         limit: u64,
     ) -> Result<Vec<LongTermMemory>>;
 
@@ -20,8 +24,24 @@ pub trait LongTermStore: Send + Sync {
         &self,
         embedding: Vec<f32>,
         user_id: u64,
+        scope: MemoryScope,
         limit: u64,
     ) -> Result<Vec<MidTermMemory>>;
 
     async fn delete_expired_midterm(&self) -> Result<()>;
+
+    /// Deletes the midterm memories promoted from the given short-term turn.
+    async fn delete_midterm_turn(&self, user_id: u64, turn_id: u64) -> Result<()>;
+
+    /// Deletes every mid/long-term memory learned in the guild.
+    async fn delete_guild(&self, guild_id: u64) -> Result<()>;
+
+    /// Returns the status of every collection, failing when the store is unreachable.
+    async fn collection_stats(&self) -> Result<Vec<CollectionStats>>;
+
+    /// Returns every stored memory, or only the user's when `user_id` is given.
+    async fn list_memories(&self, user_id: Option<u64>) -> Result<MemoryExport>;
+
+    /// Drops and recreates the collections with the configured dimension, deleting every memory.
+    async fn recreate_collections(&self) -> Result<()>;
 }
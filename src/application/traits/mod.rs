@@ -1,3 +1,6 @@
 pub mod ai_client;
+pub mod feedback_store;
 pub mod long_term_store;
+pub mod promotion_queue;
+pub mod settings_store;
 pub mod short_term_store;
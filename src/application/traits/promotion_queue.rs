@@ -0,0 +1,16 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::models::memory::PendingPromotion;
+
+#[async_trait]
+pub trait PromotionQueue: Send + Sync {
+    /// Inserts the promotion, replacing any entry with the same memory id.
+    async fn enqueue(&self, promotion: PendingPromotion) -> Result<()>;
+
+    async fn due(&self, now: i64) -> Vec<PendingPromotion>;
+
+    async fn remove(&self, memory_id: &str) -> Result<()>;
+
+    async fn depth(&self) -> usize;
+}
@@ -0,0 +1,25 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::models::settings::{GuildSettings, UserSettings};
+
+pub type SettingsUpdate = Box<dyn FnOnce(&mut GuildSettings) + Send>;
+
+pub type UserSettingsUpdate = Box<dyn FnOnce(&mut UserSettings) + Send>;
+
+#[async_trait]
+pub trait SettingsStore: Send + Sync {
+    /// Returns the default settings for guilds that have never been configured.
+    async fn guild(&self, guild_id: u64) -> GuildSettings;
+
+    /// Applies `update` atomically, persists the result and returns it.
+    async fn update_guild(&self, guild_id: u64, update: SettingsUpdate) -> Result<GuildSettings>;
+
+    async fn remove_guild(&self, guild_id: u64) -> Result<()>;
+
+    /// Returns the default settings for users that have never changed anything.
+    async fn user(&self, user_id: u64) -> UserSettings;
+
+    /// Applies `update` atomically, persists the result and returns it.
+    async fn update_user(&self, user_id: u64, update: UserSettingsUpdate) -> Result<UserSettings>;
+}
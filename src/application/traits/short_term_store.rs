@@ -1,10 +1,35 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 
-use crate::models::memory::ShortTermMessage;
+use crate::models::{health::ShortTermStats, memory::ShortTermMessage};
 
 #[async_trait]
 pub trait ShortTermStore: Send + Sync {
     async fn push(&self, channel_id: u64, message: ShortTermMessage) -> Vec<ShortTermMessage>;
 
     async fn get_context(&self, channel_id: u64) -> Vec<ShortTermMessage>;
+
+    /// Replaces every message of `turn_id` with `messages` at the position of the turn.
+    /// Returns the overflow like `push`, or `None` when the turn is no longer stored.
+    async fn replace_turn(
+        &self,
+        channel_id: u64,
+        turn_id: u64,
+        messages: Vec<ShortTermMessage>,
+    ) -> Option<Vec<ShortTermMessage>>;
+
+    /// Removes and returns every message of `turn_id`.
+    async fn remove_turn(&self, channel_id: u64, turn_id: u64) -> Vec<ShortTermMessage>;
+
+    /// Forgets the whole conversation of the channel.
+    async fn clear(&self, channel_id: u64);
+
+    async fn stats(&self) -> ShortTermStats;
+
+    /// Returns every conversation, keyed by channel.
+    async fn snapshot(&self) -> HashMap<u64, Vec<ShortTermMessage>>;
+
+    /// Puts the conversations back, keeping only the newest messages of each channel.
+    async fn restore(&self, conversations: HashMap<u64, Vec<ShortTermMessage>>);
 }
@@ -1,37 +1,49 @@
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
+use futures::StreamExt;
 use rig::{
-    completion::{Chat, Message, request::PromptError},
+    agent::MultiTurnStreamItem,
+    completion::{Message, Prompt, request::PromptError},
     embeddings::EmbeddingModel,
     prelude::*,
     providers,
+    streaming::{StreamedAssistantContent, StreamingChat},
 };
+use tokio::sync::mpsc::UnboundedSender;
 
 #[allow(unused_imports)]
 use crate::{
     application::traits::ai_client::AIClient,
     infrastructure::ai::tools::*,
-    models::memory::{ChatMessage, ChatRole},
-    shared::config::{Embedding, NLP},
+    models::memory::{ChatMessage, ChatRole, Completion, TokenUsage},
+    shared::{
+        config::{Embedding, NLP},
+        secret::Secret,
+    },
 };
 
 pub struct RigClient {
     nlp_client: rig::agent::Agent<providers::openai::responses_api::ResponsesCompletionModel>,
     embed_client: rig::providers::openai::EmbeddingModel,
+    /// 生成APIの疎通確認用。モデル一覧の取得はトークンを消費しない
+    http_client: reqwest::Client,
+    nlp_api_url: String,
+    nlp_api_key: Secret,
 }
 
 impl RigClient {
     pub async fn new(
-        nlp_api_key: String,
-        embed_api_key: String,
+        nlp_api_key: Secret,
+        embed_api_key: Secret,
         nlp: NLP,
         embedding: Embedding,
     ) -> Result<Self> {
         let system_instruction =
             std::fs::read_to_string("INSTRUCTION.md").context("Failed to read INSTRUCTION.md")?;
 
+        let nlp_api_url = nlp.api_url.trim_end_matches('/').to_string();
         let openai_comp_nlp_client = providers::openai::Client::builder()
-            .api_key(nlp_api_key)
+            .api_key(nlp_api_key.expose())
             .base_url(nlp.api_url)
             .build()
             .context("Failed to build openai nlp client")?;
@@ -44,7 +56,7 @@ impl RigClient {
             .build();
 
         let openai_comp_embed_client = providers::openai::Client::builder()
-            .api_key(embed_api_key)
+            .api_key(embed_api_key.expose())
             .base_url(embedding.api_url)
             .build()
             .context("Failed to build openai embed client")?;
@@ -54,6 +66,9 @@ impl RigClient {
         Ok(Self {
             nlp_client,
             embed_client,
+            http_client: reqwest::Client::new(),
+            nlp_api_url,
+            nlp_api_key,
         })
     }
 }
@@ -71,14 +86,59 @@ impl AIClient for RigClient {
         &self,
         prompt: ChatMessage,
         chat_history: Vec<ChatMessage>,
-    ) -> Result<String> {
+    ) -> Result<Completion> {
         let rig_prompt = to_rig_message(prompt);
-        let rig_history: Vec<Message> = chat_history.into_iter().map(to_rig_message).collect();
+        let mut rig_history: Vec<Message> = chat_history.into_iter().map(to_rig_message).collect();
 
-        self.nlp_client
-            .chat(rig_prompt, rig_history)
+        let response = self
+            .nlp_client
+            .prompt(rig_prompt)
+            .with_history(&mut rig_history)
+            .extended_details()
             .await
-            .map_err(|e: PromptError| anyhow!(e.to_string()))
+            .map_err(|e: PromptError| anyhow!(e.to_string()))?;
+
+        Ok(Completion {
+            content: response.output,
+            usage: TokenUsage {
+                input_tokens: response.total_usage.input_tokens,
+                output_tokens: response.total_usage.output_tokens,
+            },
+        })
+    }
+
+    async fn generate_streaming(
+        &self,
+        prompt: ChatMessage,
+        chat_history: Vec<ChatMessage>,
+        deltas: UnboundedSender<String>,
+    ) -> Result<Completion> {
+        let rig_history: Vec<Message> = chat_history.into_iter().map(to_rig_message).collect();
+        let mut stream = self
+            .nlp_client
+            .stream_chat(to_rig_message(prompt), rig_history)
+            .await;
+
+        let mut content = String::new();
+        let mut usage = TokenUsage::default();
+        while let Some(item) = stream.next().await {
+            match item.map_err(|e| anyhow!(e.to_string()))? {
+                MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text)) => {
+                    content.push_str(&text.text);
+                    // 受け取り側が切断しても、会話の記録のために生成は最後まで続ける
+                    let _ = deltas.send(text.text);
+                }
+                MultiTurnStreamItem::FinalResponse(response) => {
+                    usage = TokenUsage {
+                        input_tokens: response.usage().input_tokens,
+                        output_tokens: response.usage().output_tokens,
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Completion { content, usage })
     }
 
     async fn embed(&self, text: String) -> Result<Vec<f32>> {
@@ -94,4 +154,14 @@ impl AIClient for RigClient {
             .map(|e| e.vec.into_iter().map(|v| v as f32).collect())
             .ok_or_else(|| anyhow!("No embedding returned"))
     }
+
+    async fn ping_generation(&self) -> Result<()> {
+        self.http_client
+            .get(format!("{}/models", self.nlp_api_url))
+            .bearer_auth(self.nlp_api_key.expose())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
 }
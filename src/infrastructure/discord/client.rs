@@ -1,13 +1,11 @@
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use serenity::prelude::*;
+use serenity::{all::ShardManager, prelude::*};
 
-use crate::{
-    application::traits::{
-        ai_client::AIClient, long_term_store::LongTermStore, short_term_store::ShortTermStore,
-    },
-    presentation::handler::Handler,
+use crate::presentation::{
+    command::{command_registry::Data, traced_framework::TracedFramework},
+    handler::Handler,
 };
 
 pub struct DiscordClient {
@@ -15,32 +13,21 @@ pub struct DiscordClient {
 }
 
 impl DiscordClient {
-    pub async fn new(
-        discord_token: String,
-        guild_id: u64,
-        ai_client: Arc<dyn AIClient>,
-        short_term_store: Arc<dyn ShortTermStore>,
-        long_term_store: Arc<dyn LongTermStore>,
-    ) -> Result<Self> {
+    pub async fn new(discord_token: String, data: Arc<Data>) -> Result<Self> {
         let intents = GatewayIntents::GUILDS
             | GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::GUILD_MESSAGE_REACTIONS
+            | GatewayIntents::DIRECT_MESSAGE_REACTIONS
             | GatewayIntents::MESSAGE_CONTENT;
 
-        let command_framework = crate::presentation::command::command_registry::command_framework(
-            guild_id,
-            ai_client.clone(),
-            short_term_store.clone(),
-            long_term_store.clone(),
-        )
-        .await;
+        let command_framework =
+            crate::presentation::command::command_registry::command_framework(data.clone()).await;
+        let in_flight = data.in_flight.clone();
 
         let client = Client::builder(discord_token, intents)
-            .event_handler(Handler {
-                ai_client,
-                short_term_store,
-                long_term_store,
-            })
-            .framework(command_framework)
+            .event_handler(Handler { data })
+            .framework(TracedFramework::new(command_framework, in_flight))
             .await
             .context("Failed to create Discord client")?;
 
@@ -49,6 +36,10 @@ impl DiscordClient {
         })
     }
 
+    pub fn shard_manager(&self) -> Arc<ShardManager> {
+        self.discord_client.shard_manager.clone()
+    }
+
     pub async fn run(mut self) -> Result<()> {
         self.discord_client
             .start()
@@ -1,3 +1,4 @@
 pub mod ai;
 pub mod discord;
+pub mod resilience;
 pub mod store;
@@ -0,0 +1,158 @@
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{models::error::CircuitOpen, shared::config::CircuitBreaker as CircuitBreakerConfig};
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// 連続失敗が閾値を超えた依存先への呼び出しを一定時間遮断し、時間経過後に1件だけ試行を通して復旧を確認する
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    open_duration: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, config: &CircuitBreakerConfig) -> Self {
+        Self {
+            name,
+            failure_threshold: config.failure_threshold.max(1),
+            open_duration: Duration::from_secs(config.open_secs),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is_open(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.open_until.is_some()
+    }
+
+    pub async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        if !self.allow() {
+            return Err(CircuitOpen(self.name).into());
+        }
+
+        let result = f.await;
+        match &result {
+            Ok(_) => self.record_success(),
+            Err(err) => self.record_failure(err),
+        }
+        result
+    }
+
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            None => true,
+            Some(until) if Instant::now() >= until => {
+                // half-open: 試行中の1件以外は引き続き遮断する
+                state.open_until = Some(Instant::now() + self.open_duration);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.open_until.take().is_some() {
+            tracing::info!(
+                dependency = self.name,
+                "Dependency recovered; leaving degraded mode"
+            );
+        }
+        state.consecutive_failures = 0;
+    }
+
+    fn record_failure(&self, err: &anyhow::Error) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+
+        if state.consecutive_failures < self.failure_threshold {
+            return;
+        }
+
+        if state.open_until.is_none() {
+            tracing::warn!(
+                dependency = self.name,
+                failures = state.consecutive_failures,
+                "Dependency unavailable; entering degraded mode: {err}"
+            );
+        }
+        state.open_until = Some(Instant::now() + self.open_duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(failure_threshold: u32, open_secs: u64) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "test",
+            &CircuitBreakerConfig {
+                failure_threshold,
+                open_secs,
+            },
+        )
+    }
+
+    async fn fail(breaker: &CircuitBreaker) -> Result<()> {
+        breaker.call(async { anyhow::bail!("down") }).await
+    }
+
+    #[tokio::test]
+    async fn opens_after_threshold_and_fails_fast() {
+        let breaker = breaker(2, 60);
+        assert!(fail(&breaker).await.is_err());
+        assert!(!breaker.is_open());
+        assert!(fail(&breaker).await.is_err());
+        assert!(breaker.is_open());
+
+        let mut called = false;
+        let result = breaker
+            .call(async {
+                called = true;
+                Ok(())
+            })
+            .await;
+        assert!(result.unwrap_err().is::<CircuitOpen>());
+        assert!(!called);
+    }
+
+    #[tokio::test]
+    async fn success_resets_failure_count() {
+        let breaker = breaker(2, 60);
+        assert!(fail(&breaker).await.is_err());
+        breaker.call(async { Ok(()) }).await.unwrap();
+        assert!(fail(&breaker).await.is_err());
+        assert!(!breaker.is_open());
+    }
+
+    #[tokio::test]
+    async fn half_open_trial_closes_circuit_on_success() {
+        let breaker = breaker(1, 0);
+        assert!(fail(&breaker).await.is_err());
+        assert!(breaker.is_open());
+
+        breaker.call(async { Ok(()) }).await.unwrap();
+        assert!(!breaker.is_open());
+    }
+}
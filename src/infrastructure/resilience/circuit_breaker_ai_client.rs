@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    application::traits::ai_client::AIClient,
+    infrastructure::resilience::circuit_breaker::CircuitBreaker,
+    models::memory::{ChatMessage, Completion},
+};
+
+/// 埋め込みAPIのみを遮断対象とする。生成が使えない場合は応答自体ができないため素通しする
+pub struct CircuitBreakerAIClient {
+    inner: Arc<dyn AIClient>,
+    embed_breaker: Arc<CircuitBreaker>,
+}
+
+impl CircuitBreakerAIClient {
+    pub fn new(inner: Arc<dyn AIClient>, embed_breaker: Arc<CircuitBreaker>) -> Self {
+        Self {
+            inner,
+            embed_breaker,
+        }
+    }
+}
+
+#[async_trait]
+impl AIClient for CircuitBreakerAIClient {
+    async fn generate(
+        &self,
+        prompt: ChatMessage,
+        chat_history: Vec<ChatMessage>,
+    ) -> Result<Completion> {
+        self.inner.generate(prompt, chat_history).await
+    }
+
+    async fn generate_streaming(
+        &self,
+        prompt: ChatMessage,
+        chat_history: Vec<ChatMessage>,
+        deltas: UnboundedSender<String>,
+    ) -> Result<Completion> {
+        self.inner
+            .generate_streaming(prompt, chat_history, deltas)
+            .await
+    }
+
+    async fn embed(&self, text: String) -> Result<Vec<f32>> {
+        self.embed_breaker.call(self.inner.embed(text)).await
+    }
+
+    async fn ping_generation(&self) -> Result<()> {
+        self.inner.ping_generation().await
+    }
+}
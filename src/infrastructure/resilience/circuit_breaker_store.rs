@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    application::traits::long_term_store::LongTermStore,
+    infrastructure::resilience::circuit_breaker::CircuitBreaker,
+    models::{
+        health::CollectionStats,
+        memory::{LongTermMemory, MemoryExport, MemoryScope, MidTermMemory},
+    },
+};
+
+pub struct CircuitBreakerStore {
+    inner: Arc<dyn LongTermStore>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl CircuitBreakerStore {
+    pub fn new(inner: Arc<dyn LongTermStore>, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { inner, breaker }
+    }
+}
+
+#[async_trait]
+impl LongTermStore for CircuitBreakerStore {
+    async fn store_longterm(&self, memory: LongTermMemory, embedding: Vec<f32>) -> Result<()> {
+        self.breaker
+            .call(self.inner.store_longterm(memory, embedding))
+            .await
+    }
+
+    async fn store_midterm(&self, memory: MidTermMemory, embedding: Vec<f32>) -> Result<()> {
+        self.breaker
+            .call(self.inner.store_midterm(memory, embedding))
+            .await
+    }
+
+    async fn search_longterm(
+        &self,
+        embedding: Vec<f32>,
+        user_id: u64,
+        scope: MemoryScope,
+        limit: u64,
+    ) -> Result<Vec<LongTermMemory>> {
+        self.breaker
+            .call(self.inner.search_longterm(embedding, user_id, scope, limit))
+            .await
+    }
+
+    async fn search_midterm(
+        &self,
+        embedding: Vec<f32>,
+        user_id: u64,
+        scope: MemoryScope,
+        limit: u64,
+    ) -> Result<Vec<MidTermMemory>> {
+        self.breaker
+            .call(self.inner.search_midterm(embedding, user_id, scope, limit))
+            .await
+    }
+
+    async fn delete_expired_midterm(&self) -> Result<()> {
+        self.breaker.call(self.inner.delete_expired_midterm()).await
+    }
+
+    async fn delete_midterm_turn(&self, user_id: u64, turn_id: u64) -> Result<()> {
+        self.breaker
+            .call(self.inner.delete_midterm_turn(user_id, turn_id))
+            .await
+    }
+
+    async fn delete_guild(&self, guild_id: u64) -> Result<()> {
+        self.breaker.call(self.inner.delete_guild(guild_id)).await
+    }
+
+    /// 遮断中でも実際の状態を確認できるよう、ブレーカーを通さない
+    async fn collection_stats(&self) -> Result<Vec<CollectionStats>> {
+        self.inner.collection_stats().await
+    }
+
+    async fn list_memories(&self, user_id: Option<u64>) -> Result<MemoryExport> {
+        self.breaker.call(self.inner.list_memories(user_id)).await
+    }
+
+    async fn recreate_collections(&self) -> Result<()> {
+        self.breaker.call(self.inner.recreate_collections()).await
+    }
+}
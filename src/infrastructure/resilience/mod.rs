@@ -0,0 +1,3 @@
+pub mod circuit_breaker;
+pub mod circuit_breaker_ai_client;
+pub mod circuit_breaker_store;
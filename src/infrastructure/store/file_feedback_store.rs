@@ -0,0 +1,281 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, sync::RwLock};
+
+use crate::{
+    application::traits::feedback_store::FeedbackStore,
+    infrastructure::store::json_file,
+    models::feedback::{Feedback, Rating},
+};
+
+/// ファイルに1行ずつ追記する、評価の変更
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum FeedbackEvent {
+    Record(Feedback),
+    Retract {
+        turn_id: u64,
+        rater_id: u64,
+        rating: Rating,
+    },
+}
+
+/// 応答への評価をJSON Linesのファイルに追記していくストア。
+/// リアクションのたびに全件を書き直さないよう、変更だけを追記し、起動時に読み込みながら整理する
+pub struct FileFeedbackStore {
+    path: PathBuf,
+    entries: RwLock<Vec<Feedback>>,
+}
+
+impl FileFeedbackStore {
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => {
+                let (entries, needs_compaction) = replay(&contents)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                if needs_compaction {
+                    compact(&path, &entries).await?;
+                }
+                entries
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => load_legacy(&path).await?,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+
+        Ok(Self {
+            path,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    async fn append(&self, event: &FeedbackEvent) -> Result<()> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+fn apply(entries: &mut Vec<Feedback>, event: FeedbackEvent) {
+    match event {
+        FeedbackEvent::Record(feedback) => {
+            entries.retain(|f| !(f.turn_id == feedback.turn_id && f.rater_id == feedback.rater_id));
+            entries.push(feedback);
+        }
+        FeedbackEvent::Retract {
+            turn_id,
+            rater_id,
+            rating,
+        } => {
+            entries.retain(|f| {
+                !(f.turn_id == turn_id && f.rater_id == rater_id && f.rating == rating)
+            });
+        }
+    }
+}
+
+/// 追記された変更を順に当てはめる。取り消しや付け直しで今の評価より行が多ければ、詰め直しが必要とする。
+/// 以前の、評価の配列を丸ごと書いた形式も読み、詰め直しで新しい形式に書き換える
+fn replay(contents: &str) -> Result<(Vec<Feedback>, bool)> {
+    if contents.trim_start().starts_with('[') {
+        let entries: Vec<Feedback> = serde_json::from_str(contents)?;
+        return Ok((entries, true));
+    }
+
+    let mut entries = Vec::new();
+    let mut events = 0;
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        apply(&mut entries, serde_json::from_str(line)?);
+        events += 1;
+    }
+    let needs_compaction = events != entries.len();
+    Ok((entries, needs_compaction))
+}
+
+/// 書き込み途中でプロセスが落ちてもファイルが壊れないよう、一時ファイル経由で置き換える
+async fn compact(path: &Path, entries: &[Feedback]) -> Result<()> {
+    let mut bytes = Vec::new();
+    for feedback in entries {
+        serde_json::to_writer(&mut bytes, &FeedbackEvent::Record(feedback.clone()))?;
+        bytes.push(b'\n');
+    }
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, bytes).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+/// 以前の既定の保存先（拡張子 `.json`）に残っている評価を取り込む
+async fn load_legacy(path: &Path) -> Result<Vec<Feedback>> {
+    let legacy_path = path.with_extension("json");
+    if legacy_path == path {
+        return Ok(Vec::new());
+    }
+    let Some(entries) = json_file::load::<Vec<Feedback>>(&legacy_path).await? else {
+        return Ok(Vec::new());
+    };
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    compact(path, &entries).await?;
+    tracing::info!(
+        from = %legacy_path.display(),
+        to = %path.display(),
+        entries = entries.len(),
+        "Migrated feedback to JSON Lines"
+    );
+    Ok(entries)
+}
+
+#[async_trait]
+impl FeedbackStore for FileFeedbackStore {
+    async fn record(&self, feedback: Feedback) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        let event = FeedbackEvent::Record(feedback);
+        self.append(&event).await?;
+        apply(&mut entries, event);
+        Ok(())
+    }
+
+    async fn retract(&self, turn_id: u64, rater_id: u64, rating: Rating) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        let matches =
+            |f: &Feedback| f.turn_id == turn_id && f.rater_id == rater_id && f.rating == rating;
+        if !entries.iter().any(matches) {
+            return Ok(());
+        }
+
+        let event = FeedbackEvent::Retract {
+            turn_id,
+            rater_id,
+            rating,
+        };
+        self.append(&event).await?;
+        apply(&mut entries, event);
+        Ok(())
+    }
+
+    async fn list(&self, guild_id: Option<u64>) -> Vec<Feedback> {
+        self.entries
+            .read()
+            .await
+            .iter()
+            .filter(|f| f.guild_id == guild_id)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path() -> PathBuf {
+        crate::test_support::temp_path().join("feedback.jsonl")
+    }
+
+    fn feedback(turn_id: u64, rater_id: u64, rating: Rating) -> Feedback {
+        Feedback {
+            turn_id,
+            guild_id: Some(1),
+            channel_id: 10,
+            requester_id: 2,
+            rater_id,
+            rating,
+            prompt: "<message>hi</message>".to_string(),
+            memories: Vec::new(),
+            model: "model".to_string(),
+            instruction_version: "v".to_string(),
+            response: "hello".to_string(),
+            rated_at: 0,
+        }
+    }
+
+    fn line_count(path: &Path) -> usize {
+        std::fs::read_to_string(path).unwrap().lines().count()
+    }
+
+    #[tokio::test]
+    async fn rerating_replaces_previous_rating() {
+        let store = FileFeedbackStore::new(temp_path()).await.unwrap();
+        store.record(feedback(1, 2, Rating::Good)).await.unwrap();
+        store.record(feedback(1, 2, Rating::Bad)).await.unwrap();
+        store.record(feedback(1, 3, Rating::Good)).await.unwrap();
+
+        let entries = store.list(Some(1)).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].rating, Rating::Bad);
+    }
+
+    #[tokio::test]
+    async fn retract_only_removes_matching_rating() {
+        let path = temp_path();
+        {
+            let store = FileFeedbackStore::new(&path).await.unwrap();
+            store.record(feedback(1, 2, Rating::Bad)).await.unwrap();
+            // 👍を外しても、後から付けた👎は残る
+            store.retract(1, 2, Rating::Good).await.unwrap();
+        }
+
+        let reloaded = FileFeedbackStore::new(&path).await.unwrap();
+        assert_eq!(reloaded.list(Some(1)).await.len(), 1);
+        reloaded.retract(1, 2, Rating::Bad).await.unwrap();
+        assert!(reloaded.list(Some(1)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn changes_are_appended_and_compacted_on_reload() {
+        let path = temp_path();
+        {
+            let store = FileFeedbackStore::new(&path).await.unwrap();
+            store.record(feedback(1, 2, Rating::Good)).await.unwrap();
+            store.record(feedback(1, 2, Rating::Bad)).await.unwrap();
+            store.record(feedback(2, 2, Rating::Good)).await.unwrap();
+            store.retract(2, 2, Rating::Good).await.unwrap();
+        }
+        assert_eq!(line_count(&path), 4);
+
+        let reloaded = FileFeedbackStore::new(&path).await.unwrap();
+        let entries = reloaded.list(Some(1)).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].rating, Rating::Bad);
+        assert_eq!(line_count(&path), 1);
+    }
+
+    #[tokio::test]
+    async fn legacy_json_array_is_migrated() {
+        let path = temp_path();
+        json_file::save(
+            &path.with_extension("json"),
+            &[feedback(1, 2, Rating::Good)],
+        )
+        .await
+        .unwrap();
+
+        let store = FileFeedbackStore::new(&path).await.unwrap();
+        assert_eq!(store.list(Some(1)).await.len(), 1);
+        assert_eq!(line_count(&path), 1);
+    }
+}
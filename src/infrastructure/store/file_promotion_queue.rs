@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::{
+    application::traits::promotion_queue::PromotionQueue, infrastructure::store::json_file,
+    models::memory::PendingPromotion,
+};
+
+/// 昇格に失敗した中期記憶をJSONファイルに永続化するキュー
+pub struct FilePromotionQueue {
+    /// `None` なら保存しない
+    path: Option<PathBuf>,
+    entries: Arc<RwLock<HashMap<String, PendingPromotion>>>,
+}
+
+impl FilePromotionQueue {
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let pending: Vec<PendingPromotion> = json_file::load(&path).await?.unwrap_or_default();
+        let entries = pending
+            .into_iter()
+            .map(|p| (p.memory.id.clone(), p))
+            .collect();
+
+        Ok(Self {
+            path: Some(path),
+            entries: Arc::new(RwLock::new(entries)),
+        })
+    }
+
+    /// ファイルに保存しないキュー。ボットと並べて動かすCLIが、ボットのキューのファイルを書き換えないように使う
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: Arc::default(),
+        }
+    }
+
+    async fn persist(&self, entries: &HashMap<String, PendingPromotion>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let pending: Vec<&PendingPromotion> = entries.values().collect();
+        json_file::save(path, &pending).await
+    }
+}
+
+#[async_trait]
+impl PromotionQueue for FilePromotionQueue {
+    async fn enqueue(&self, promotion: PendingPromotion) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        entries.insert(promotion.memory.id.clone(), promotion);
+        self.persist(&entries).await
+    }
+
+    async fn due(&self, now: i64) -> Vec<PendingPromotion> {
+        let entries = self.entries.read().await;
+        let mut due: Vec<PendingPromotion> = entries
+            .values()
+            .filter(|p| p.next_attempt_at <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|p| p.next_attempt_at);
+        due
+    }
+
+    async fn remove(&self, memory_id: &str) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        if entries.remove(memory_id).is_some() {
+            self.persist(&entries).await?;
+        }
+        Ok(())
+    }
+
+    async fn depth(&self) -> usize {
+        self.entries.read().await.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::memory::MidTermMemory;
+
+    fn temp_path() -> PathBuf {
+        crate::test_support::temp_path().join("promotion_queue.json")
+    }
+
+    fn make_pending(id: &str, next_attempt_at: i64) -> PendingPromotion {
+        PendingPromotion {
+            memory: MidTermMemory {
+                id: id.to_string(),
+                user_id: 1,
+                channel_id: 100,
+                summary: "[user] hello".to_string(),
+                created_at: 0,
+                expires_at: 999,
+                direct_message: false,
+                guild_id: Some(1),
+                turn_id: 0,
+            },
+            attempts: 1,
+            next_attempt_at,
+            last_error: "embed failed".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn due_returns_only_ready_entries() {
+        let queue = FilePromotionQueue::new(temp_path()).await.unwrap();
+        queue.enqueue(make_pending("a", 10)).await.unwrap();
+        queue.enqueue(make_pending("b", 100)).await.unwrap();
+
+        let due = queue.due(50).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].memory.id, "a");
+        assert_eq!(queue.depth().await, 2);
+    }
+
+    #[tokio::test]
+    async fn enqueue_replaces_same_memory_id() {
+        let queue = FilePromotionQueue::new(temp_path()).await.unwrap();
+        queue.enqueue(make_pending("a", 10)).await.unwrap();
+
+        let mut retried = make_pending("a", 20);
+        retried.attempts = 2;
+        queue.enqueue(retried).await.unwrap();
+
+        let due = queue.due(100).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn entries_survive_reload() {
+        let path = temp_path();
+        {
+            let queue = FilePromotionQueue::new(&path).await.unwrap();
+            queue.enqueue(make_pending("a", 10)).await.unwrap();
+            queue.enqueue(make_pending("b", 10)).await.unwrap();
+            queue.remove("a").await.unwrap();
+        }
+
+        let reloaded = FilePromotionQueue::new(&path).await.unwrap();
+        let due = reloaded.due(100).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].memory.id, "b");
+    }
+}
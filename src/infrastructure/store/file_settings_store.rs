@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::{
+    application::traits::settings_store::{SettingsStore, SettingsUpdate, UserSettingsUpdate},
+    infrastructure::store::json_file,
+    models::settings::{GuildSettings, UserSettings},
+};
+
+/// サーバーごとと利用者ごとの設定を、それぞれJSONファイルに永続化するストア
+pub struct FileSettingsStore {
+    path: PathBuf,
+    guilds: RwLock<HashMap<u64, GuildSettings>>,
+    user_path: PathBuf,
+    users: RwLock<HashMap<u64, UserSettings>>,
+}
+
+impl FileSettingsStore {
+    pub async fn new(path: impl AsRef<Path>, user_path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let guilds = json_file::load(&path).await?.unwrap_or_default();
+        let user_path = user_path.as_ref().to_path_buf();
+        let users = json_file::load(&user_path).await?.unwrap_or_default();
+
+        Ok(Self {
+            path,
+            guilds: RwLock::new(guilds),
+            user_path,
+            users: RwLock::new(users),
+        })
+    }
+}
+
+#[async_trait]
+impl SettingsStore for FileSettingsStore {
+    async fn guild(&self, guild_id: u64) -> GuildSettings {
+        self.guilds
+            .read()
+            .await
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn update_guild(&self, guild_id: u64, update: SettingsUpdate) -> Result<GuildSettings> {
+        update_entry(&self.path, &self.guilds, guild_id, update).await
+    }
+
+    async fn remove_guild(&self, guild_id: u64) -> Result<()> {
+        let mut guilds = self.guilds.write().await;
+        if !guilds.contains_key(&guild_id) {
+            return Ok(());
+        }
+
+        let mut next = guilds.clone();
+        next.remove(&guild_id);
+        json_file::save(&self.path, &next).await?;
+        *guilds = next;
+
+        Ok(())
+    }
+
+    async fn user(&self, user_id: u64) -> UserSettings {
+        self.users
+            .read()
+            .await
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn update_user(&self, user_id: u64, update: UserSettingsUpdate) -> Result<UserSettings> {
+        update_entry(&self.user_path, &self.users, user_id, update).await
+    }
+}
+
+async fn update_entry<T: Clone + Default + Serialize>(
+    path: &Path,
+    entries: &RwLock<HashMap<u64, T>>,
+    id: u64,
+    update: Box<dyn FnOnce(&mut T) + Send>,
+) -> Result<T> {
+    let mut entries = entries.write().await;
+    let mut entry = entries.get(&id).cloned().unwrap_or_default();
+    update(&mut entry);
+
+    // 保存に失敗した場合はメモリ上の設定も変えない
+    let mut next = entries.clone();
+    next.insert(id, entry.clone());
+    json_file::save(path, &next).await?;
+    *entries = next;
+
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{locale::Locale, settings::ChannelMode},
+        test_support::temp_path,
+    };
+
+    async fn open(dir: &Path) -> FileSettingsStore {
+        FileSettingsStore::new(
+            dir.join("guild_settings.json"),
+            dir.join("user_settings.json"),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unknown_guild_has_default_settings() {
+        let store = open(&temp_path()).await;
+        let settings = store.guild(1).await;
+        assert_eq!(settings.channel_mode(10), ChannelMode::MentionOnly);
+    }
+
+    #[tokio::test]
+    async fn channel_mode_survives_reload() {
+        let dir = temp_path();
+        {
+            let store = open(&dir).await;
+            store
+                .update_guild(
+                    1,
+                    Box::new(|s| s.set_channel_mode(10, ChannelMode::AutoRespond)),
+                )
+                .await
+                .unwrap();
+        }
+
+        let reloaded = open(&dir).await;
+        assert_eq!(
+            reloaded.guild(1).await.channel_mode(10),
+            ChannelMode::AutoRespond
+        );
+        assert_eq!(
+            reloaded.guild(2).await.channel_mode(10),
+            ChannelMode::MentionOnly
+        );
+    }
+
+    #[tokio::test]
+    async fn removed_guild_falls_back_to_defaults() {
+        let dir = temp_path();
+        let store = open(&dir).await;
+        store
+            .update_guild(
+                1,
+                Box::new(|s| s.set_channel_mode(10, ChannelMode::AutoRespond)),
+            )
+            .await
+            .unwrap();
+        store.remove_guild(1).await.unwrap();
+
+        let reloaded = open(&dir).await;
+        assert_eq!(reloaded.guild(1).await, GuildSettings::default());
+    }
+
+    #[tokio::test]
+    async fn user_locale_survives_reload() {
+        let dir = temp_path();
+        {
+            let store = open(&dir).await;
+            store
+                .update_user(1, Box::new(|s| s.locale = Some(Locale::En)))
+                .await
+                .unwrap();
+        }
+
+        let reloaded = open(&dir).await;
+        assert_eq!(reloaded.user(1).await.locale, Some(Locale::En));
+        assert_eq!(reloaded.user(2).await, UserSettings::default());
+        assert_eq!(reloaded.guild(1).await, GuildSettings::default());
+    }
+}
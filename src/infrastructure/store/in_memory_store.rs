@@ -7,7 +7,8 @@ use async_trait::async_trait;
 use tokio::sync::RwLock;
 
 use crate::{
-    application::traits::short_term_store::ShortTermStore, models::memory::ShortTermMessage,
+    application::traits::short_term_store::ShortTermStore,
+    models::{health::ShortTermStats, memory::ShortTermMessage},
 };
 
 pub struct InMemoryStore {
@@ -31,13 +32,7 @@ impl ShortTermStore for InMemoryStore {
         let queue = store.entry(channel_id).or_insert_with(VecDeque::new);
         queue.push_back(message);
 
-        let mut overflow = Vec::new();
-        while queue.len() > self.max_short_term_messages {
-            if let Some(old) = queue.pop_front() {
-                overflow.push(old);
-            }
-        }
-        overflow
+        drain_overflow(queue, self.max_short_term_messages)
     }
 
     async fn get_context(&self, channel_id: u64) -> Vec<ShortTermMessage> {
@@ -47,6 +42,77 @@ impl ShortTermStore for InMemoryStore {
             .map(|q| q.iter().cloned().collect())
             .unwrap_or_default()
     }
+
+    async fn replace_turn(
+        &self,
+        channel_id: u64,
+        turn_id: u64,
+        messages: Vec<ShortTermMessage>,
+    ) -> Option<Vec<ShortTermMessage>> {
+        let mut store = self.conversations.write().await;
+        let queue = store.get_mut(&channel_id)?;
+        let position = queue.iter().position(|m| m.turn_id == turn_id)?;
+
+        queue.retain(|m| m.turn_id != turn_id);
+        for (offset, message) in messages.into_iter().enumerate() {
+            queue.insert(position + offset, message);
+        }
+
+        Some(drain_overflow(queue, self.max_short_term_messages))
+    }
+
+    async fn remove_turn(&self, channel_id: u64, turn_id: u64) -> Vec<ShortTermMessage> {
+        let mut store = self.conversations.write().await;
+        let Some(queue) = store.get_mut(&channel_id) else {
+            return Vec::new();
+        };
+
+        let (removed, kept): (Vec<_>, Vec<_>) = queue.drain(..).partition(|m| m.turn_id == turn_id);
+        *queue = kept.into();
+        removed
+    }
+
+    async fn clear(&self, channel_id: u64) {
+        self.conversations.write().await.remove(&channel_id);
+    }
+
+    async fn stats(&self) -> ShortTermStats {
+        let store = self.conversations.read().await;
+        ShortTermStats {
+            channels: store.len(),
+            messages: store.values().map(VecDeque::len).sum(),
+        }
+    }
+
+    async fn snapshot(&self) -> HashMap<u64, Vec<ShortTermMessage>> {
+        let store = self.conversations.read().await;
+        store
+            .iter()
+            .map(|(channel_id, queue)| (*channel_id, queue.iter().cloned().collect()))
+            .collect()
+    }
+
+    async fn restore(&self, conversations: HashMap<u64, Vec<ShortTermMessage>>) {
+        let mut store = self.conversations.write().await;
+        for (channel_id, messages) in conversations {
+            let mut queue: VecDeque<ShortTermMessage> = messages.into();
+            drain_overflow(&mut queue, self.max_short_term_messages);
+            store.insert(channel_id, queue);
+        }
+    }
+}
+
+fn drain_overflow(
+    queue: &mut VecDeque<ShortTermMessage>,
+    max_messages: usize,
+) -> Vec<ShortTermMessage> {
+    let mut overflow = Vec::new();
+    while queue.len() > max_messages {
+        if let Some(old) = queue.pop_front() {
+            overflow.push(old);
+        }
+    }
+    overflow
 }
 
 #[cfg(test)]
@@ -55,11 +121,16 @@ mod tests {
     use crate::models::memory::Role;
 
     fn make_msg(content: &str) -> ShortTermMessage {
+        make_turn_msg(content, 0)
+    }
+
+    fn make_turn_msg(content: &str, turn_id: u64) -> ShortTermMessage {
         ShortTermMessage {
             role: Role::User,
             user_id: 1,
             content: content.to_string(),
             timestamp: 0,
+            turn_id,
         }
     }
 
@@ -110,4 +181,75 @@ mod tests {
         assert_eq!(ctx100[0].content, "ch100");
         assert_eq!(ctx200[0].content, "ch200");
     }
+
+    #[tokio::test]
+    async fn replace_turn_keeps_position() {
+        let store = InMemoryStore::new(10);
+        store.push(100, make_turn_msg("q1", 1)).await;
+        store.push(100, make_turn_msg("a1", 1)).await;
+        store.push(100, make_turn_msg("q2", 2)).await;
+        store.push(100, make_turn_msg("a2", 2)).await;
+
+        let overflow = store
+            .replace_turn(
+                100,
+                1,
+                vec![make_turn_msg("q1'", 1), make_turn_msg("a1'", 1)],
+            )
+            .await;
+        assert_eq!(overflow.map(|o| o.len()), Some(0));
+
+        let contents: Vec<String> = store
+            .get_context(100)
+            .await
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["q1'", "a1'", "q2", "a2"]);
+    }
+
+    #[tokio::test]
+    async fn remove_turn_returns_removed_messages() {
+        let store = InMemoryStore::new(10);
+        store.push(100, make_turn_msg("q1", 1)).await;
+        store.push(100, make_turn_msg("q2", 2)).await;
+        store.push(100, make_turn_msg("a1", 1)).await;
+
+        let removed = store.remove_turn(100, 1).await;
+        assert_eq!(removed.len(), 2);
+
+        let ctx = store.get_context(100).await;
+        assert_eq!(ctx.len(), 1);
+        assert_eq!(ctx[0].content, "q2");
+    }
+
+    #[tokio::test]
+    async fn replace_missing_turn_returns_none() {
+        let store = InMemoryStore::new(10);
+        store.push(100, make_turn_msg("q1", 1)).await;
+
+        assert!(store.replace_turn(100, 2, Vec::new()).await.is_none());
+        assert!(store.replace_turn(200, 1, Vec::new()).await.is_none());
+        assert_eq!(store.get_context(100).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn restore_keeps_newest_messages_of_snapshot() {
+        let store = InMemoryStore::new(5);
+        store.push(100, make_msg("msg1")).await;
+        store.push(100, make_msg("msg2")).await;
+        store.push(100, make_msg("msg3")).await;
+        let snapshot = store.snapshot().await;
+
+        let restored = InMemoryStore::new(2);
+        restored.restore(snapshot).await;
+
+        let contents: Vec<String> = restored
+            .get_context(100)
+            .await
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["msg2", "msg3"]);
+    }
 }
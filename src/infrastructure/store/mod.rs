@@ -1,2 +1,7 @@
+pub mod file_feedback_store;
+pub mod file_promotion_queue;
+pub mod file_settings_store;
 pub mod in_memory_store;
+pub mod json_file;
+pub mod short_term_snapshot;
 pub mod vector_store;
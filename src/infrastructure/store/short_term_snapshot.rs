@@ -0,0 +1,66 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    application::traits::short_term_store::ShortTermStore, infrastructure::store::json_file,
+    models::memory::ShortTermMessage,
+};
+
+/// 終了時に短期記憶をファイルへ書き出す。書き出したメッセージの件数を返す
+pub async fn save(path: &Path, short_term_store: &dyn ShortTermStore) -> Result<usize> {
+    let conversations = short_term_store.snapshot().await;
+    let messages = conversations.values().map(Vec::len).sum();
+    json_file::save(path, &conversations).await?;
+    Ok(messages)
+}
+
+/// 前回の終了時に書き出した短期記憶を読み戻す。古い会話を再び読み込まないよう、読んだファイルは消す
+pub async fn restore(path: &Path, short_term_store: &dyn ShortTermStore) -> Result<usize> {
+    let Some(conversations) = json_file::load::<HashMap<u64, Vec<ShortTermMessage>>>(path).await?
+    else {
+        return Ok(0);
+    };
+    let messages = conversations.values().map(Vec::len).sum();
+
+    short_term_store.restore(conversations).await;
+    tokio::fs::remove_file(path)
+        .await
+        .with_context(|| format!("Failed to remove {}", path.display()))?;
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        infrastructure::store::in_memory_store::InMemoryStore, models::memory::Role,
+        test_support::temp_path,
+    };
+
+    #[tokio::test]
+    async fn conversations_survive_a_restart_once() {
+        let path = temp_path().with_extension("json");
+        let before = InMemoryStore::new(10);
+        before
+            .push(
+                100,
+                ShortTermMessage {
+                    role: Role::User,
+                    user_id: 1,
+                    content: "hello".to_string(),
+                    timestamp: 0,
+                    turn_id: 1,
+                },
+            )
+            .await;
+
+        assert_eq!(save(&path, &before).await.unwrap(), 1);
+
+        let after = InMemoryStore::new(10);
+        assert_eq!(restore(&path, &after).await.unwrap(), 1);
+        assert_eq!(after.get_context(100).await[0].content, "hello");
+
+        assert_eq!(restore(&path, &InMemoryStore::new(10)).await.unwrap(), 0);
+    }
+}
@@ -3,54 +3,133 @@ use async_trait::async_trait;
 use qdrant_client::{
     Payload, Qdrant,
     qdrant::{
-        Condition, CreateCollectionBuilder, DeletePointsBuilder, Distance, Filter, PointStruct,
-        QueryPointsBuilder, Range, UpsertPointsBuilder, VectorParamsBuilder,
+        CollectionStatus, Condition, CreateCollectionBuilder, DeletePointsBuilder, Distance,
+        Filter, PointStruct, QueryPointsBuilder, Range, ScrollPointsBuilder,
+        SetPayloadPointsBuilder, UpsertPointsBuilder, VectorParamsBuilder,
     },
 };
+use tokio::sync::OnceCell;
 
 use crate::{
     application::traits::long_term_store::LongTermStore,
-    models::memory::{LongTermMemory, MidTermMemory},
+    models::{
+        health::CollectionStats,
+        memory::{LongTermMemory, MemoryExport, MemoryScope, MidTermMemory},
+    },
 };
 
 const MIDTERM_COLLECTION_NAME: &str = "midterm_memory";
 const LONGTERM_COLLECTION_NAME: &str = "longterm_memory";
+/// 全件を読み出す時に1回で取得する件数
+const SCROLL_PAGE_SIZE: u32 = 256;
 
 pub struct VectorStore {
     qdrant_client: Qdrant,
+    dimension: u64,
+    collections_ready: OnceCell<()>,
 }
 
 impl VectorStore {
-    pub async fn new(url: &str, dimension: u64) -> Result<Self> {
+    /// 接続は最初のリクエスト時に行われるため、Qdrantが起動していなくても生成できる
+    pub fn new(url: &str, dimension: u64) -> Result<Self> {
         let client = Qdrant::from_url(url).build()?;
 
-        if !client.collection_exists(MIDTERM_COLLECTION_NAME).await? {
-            client
-                .create_collection(
-                    CreateCollectionBuilder::new(MIDTERM_COLLECTION_NAME)
-                        .vectors_config(VectorParamsBuilder::new(dimension, Distance::Cosine)),
-                )
-                .await?;
-        }
+        Ok(Self {
+            qdrant_client: client,
+            dimension,
+            collections_ready: OnceCell::new(),
+        })
+    }
 
-        if !client.collection_exists(LONGTERM_COLLECTION_NAME).await? {
-            client
-                .create_collection(
-                    CreateCollectionBuilder::new(LONGTERM_COLLECTION_NAME)
-                        .vectors_config(VectorParamsBuilder::new(dimension, Distance::Cosine)),
+    /// サーバーの区別が無かった頃の記憶を、`guild_id` のサーバーのものとして扱えるようにする
+    pub async fn assign_legacy_guild(&self, guild_id: u64) -> Result<()> {
+        self.ensure_collections().await?;
+
+        let payload: Payload = Payload::try_from(serde_json::json!({ "guild_id": guild_id }))
+            .map_err(|_| anyhow::anyhow!("Failed to build guild payload"))?;
+
+        for (name, legacy) in [
+            (MIDTERM_COLLECTION_NAME, legacy_midterm_filter()),
+            (LONGTERM_COLLECTION_NAME, legacy_longterm_filter()),
+        ] {
+            self.qdrant_client
+                .set_payload(
+                    SetPayloadPointsBuilder::new(name, payload.clone())
+                        .points_selector(legacy)
+                        .wait(true),
                 )
                 .await?;
         }
 
-        Ok(Self {
-            qdrant_client: client,
-        })
+        Ok(())
+    }
+
+    /// コレクションが無ければ作成する。失敗した場合は次回の呼び出しで再試行される
+    pub async fn ensure_collections(&self) -> Result<()> {
+        self.collections_ready
+            .get_or_try_init(|| async {
+                for name in [MIDTERM_COLLECTION_NAME, LONGTERM_COLLECTION_NAME] {
+                    if !self.qdrant_client.collection_exists(name).await? {
+                        self.create_collection(name).await?;
+                    }
+                }
+                Ok::<(), anyhow::Error>(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_collection(&self, name: &str) -> Result<()> {
+        self.qdrant_client
+            .create_collection(
+                CreateCollectionBuilder::new(name)
+                    .vectors_config(VectorParamsBuilder::new(self.dimension, Distance::Cosine)),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// コレクションの全件（`filter` があれば一致するもの）をページごとに読み出す
+    async fn scroll_all<T: serde::de::DeserializeOwned>(
+        &self,
+        name: &str,
+        filter: Option<Filter>,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut offset = None;
+        loop {
+            let mut request = ScrollPointsBuilder::new(name)
+                .limit(SCROLL_PAGE_SIZE)
+                .with_payload(true);
+            if let Some(filter) = filter.clone() {
+                request = request.filter(filter);
+            }
+            if let Some(offset) = offset {
+                request = request.offset(offset);
+            }
+
+            let response = self.qdrant_client.scroll(request).await?;
+            for point in response.result {
+                items.push(serde_json::from_value(serde_json::to_value(
+                    point.payload,
+                )?)?);
+            }
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => return Ok(items),
+            }
+        }
     }
 }
 
 #[async_trait]
 impl LongTermStore for VectorStore {
     async fn store_longterm(&self, memory: LongTermMemory, embedding: Vec<f32>) -> Result<()> {
+        self.ensure_collections().await?;
+
         let payload_json = serde_json::to_value(&memory)?;
         let payload: Payload = Payload::try_from(payload_json)
             .map_err(|_| anyhow::anyhow!("Failed to convert memory to payload"))?;
@@ -69,6 +148,8 @@ impl LongTermStore for VectorStore {
     }
 
     async fn store_midterm(&self, memory: MidTermMemory, embedding: Vec<f32>) -> Result<()> {
+        self.ensure_collections().await?;
+
         let payload_json = serde_json::to_value(&memory)?;
         let payload: Payload = Payload::try_from(payload_json)
             .map_err(|_| anyhow::anyhow!("Failed to convert memory to payload"))?;
@@ -90,17 +171,17 @@ impl LongTermStore for VectorStore {
         &self,
         embedding: Vec<f32>,
         user_id: u64,
+        scope: MemoryScope,
         limit: u64,
     ) -> Result<Vec<LongTermMemory>> {
+        self.ensure_collections().await?;
+
         let response = self
             .qdrant_client
             .query(
                 QueryPointsBuilder::new(LONGTERM_COLLECTION_NAME)
                     .query(embedding)
-                    .filter(Filter::must([Condition::matches(
-                        "user_id",
-                        user_id as i64,
-                    )]))
+                    .filter(longterm_filter(user_id, scope))
                     .limit(limit)
                     .with_payload(true),
             )
@@ -120,17 +201,17 @@ impl LongTermStore for VectorStore {
         &self,
         embedding: Vec<f32>,
         user_id: u64,
+        scope: MemoryScope,
         limit: u64,
     ) -> Result<Vec<MidTermMemory>> {
+        self.ensure_collections().await?;
+
         let response = self
             .qdrant_client
             .query(
                 QueryPointsBuilder::new(MIDTERM_COLLECTION_NAME)
                     .query(embedding)
-                    .filter(Filter::must([Condition::matches(
-                        "user_id",
-                        user_id as i64,
-                    )]))
+                    .filter(midterm_filter(user_id, scope))
                     .limit(limit)
                     .with_payload(true),
             )
@@ -147,6 +228,8 @@ impl LongTermStore for VectorStore {
     }
 
     async fn delete_expired_midterm(&self) -> Result<()> {
+        self.ensure_collections().await?;
+
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -171,4 +254,217 @@ impl LongTermStore for VectorStore {
         tracing::info!("Deleted expired midterm memories");
         Ok(())
     }
+
+    async fn delete_midterm_turn(&self, user_id: u64, turn_id: u64) -> Result<()> {
+        self.ensure_collections().await?;
+
+        let filter = Filter::must([
+            Condition::matches("user_id", user_id as i64),
+            Condition::matches("turn_id", turn_id as i64),
+        ]);
+
+        self.qdrant_client
+            .delete_points(
+                DeletePointsBuilder::new(MIDTERM_COLLECTION_NAME)
+                    .points(filter)
+                    .wait(true),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_guild(&self, guild_id: u64) -> Result<()> {
+        self.ensure_collections().await?;
+
+        for name in [MIDTERM_COLLECTION_NAME, LONGTERM_COLLECTION_NAME] {
+            self.qdrant_client
+                .delete_points(
+                    DeletePointsBuilder::new(name)
+                        .points(Filter::must([Condition::matches(
+                            "guild_id",
+                            guild_id as i64,
+                        )]))
+                        .wait(true),
+                )
+                .await?;
+        }
+
+        tracing::info!(guild_id, "Deleted memories of guild");
+        Ok(())
+    }
+
+    async fn collection_stats(&self) -> Result<Vec<CollectionStats>> {
+        self.qdrant_client.health_check().await?;
+        self.ensure_collections().await?;
+
+        let mut stats = Vec::new();
+        for name in [MIDTERM_COLLECTION_NAME, LONGTERM_COLLECTION_NAME] {
+            let info = self.qdrant_client.collection_info(name).await?.result;
+            stats.push(CollectionStats {
+                name: name.to_string(),
+                status: info
+                    .as_ref()
+                    .and_then(|i| CollectionStatus::try_from(i.status).ok())
+                    .map(|s| s.as_str_name().to_lowercase())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                points: info.and_then(|i| i.points_count).unwrap_or_default(),
+            });
+        }
+
+        Ok(stats)
+    }
+
+    async fn list_memories(&self, user_id: Option<u64>) -> Result<MemoryExport> {
+        self.ensure_collections().await?;
+
+        let filter = user_id.map(|id| Filter::must([Condition::matches("user_id", id as i64)]));
+
+        Ok(MemoryExport {
+            midterm: self
+                .scroll_all(MIDTERM_COLLECTION_NAME, filter.clone())
+                .await?,
+            longterm: self.scroll_all(LONGTERM_COLLECTION_NAME, filter).await?,
+        })
+    }
+
+    async fn recreate_collections(&self) -> Result<()> {
+        for name in [MIDTERM_COLLECTION_NAME, LONGTERM_COLLECTION_NAME] {
+            if self.qdrant_client.collection_exists(name).await? {
+                self.qdrant_client.delete_collection(name).await?;
+            }
+            self.create_collection(name).await?;
+        }
+
+        tracing::info!(dimension = self.dimension, "Recreated memory collections");
+        Ok(())
+    }
+}
+
+/// DMの記憶はDMでのみ、サーバーの記憶はそのサーバーでのみ検索する
+fn midterm_filter(user_id: u64, scope: MemoryScope) -> Filter {
+    let user = Condition::matches("user_id", user_id as i64);
+
+    match scope {
+        MemoryScope::DirectMessage => {
+            Filter::must([user, Condition::matches("direct_message", true)])
+        }
+        MemoryScope::Guild(guild_id) => {
+            Filter::must([user, Condition::matches("guild_id", guild_id as i64)])
+        }
+    }
+}
+
+/// DMで知った事実は `guild_id` が `null` になる。キーごと無いのはサーバーを区別する前の事実で、DMには出さない
+fn longterm_filter(user_id: u64, scope: MemoryScope) -> Filter {
+    let user = Condition::matches("user_id", user_id as i64);
+
+    match scope {
+        MemoryScope::DirectMessage => Filter::must([user, Condition::is_null("guild_id")]),
+        MemoryScope::Guild(guild_id) => {
+            Filter::must([user, Condition::matches("guild_id", guild_id as i64)])
+        }
+    }
+}
+
+/// サーバーを区別する前の、DM以外の中期記憶
+fn legacy_midterm_filter() -> Filter {
+    Filter {
+        must: vec![Condition::is_empty("guild_id")],
+        must_not: vec![Condition::matches("direct_message", true)],
+        ..Default::default()
+    }
+}
+
+/// サーバーを区別する前の長期記憶。DMで知った事実（`guild_id` が `null`）は含めない
+fn legacy_longterm_filter() -> Filter {
+    Filter {
+        must: vec![Condition::is_empty("guild_id")],
+        must_not: vec![Condition::is_null("guild_id")],
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: u64 = 1;
+    const GUILD: u64 = 10;
+
+    fn user() -> Condition {
+        Condition::matches("user_id", USER as i64)
+    }
+
+    fn in_guild(guild_id: u64) -> Condition {
+        Condition::matches("guild_id", guild_id as i64)
+    }
+
+    #[test]
+    fn midterm_direct_messages_only_recall_direct_messages() {
+        let filter = midterm_filter(USER, MemoryScope::DirectMessage);
+
+        assert!(filter.must.contains(&user()));
+        assert!(
+            filter
+                .must
+                .contains(&Condition::matches("direct_message", true))
+        );
+        assert!(!filter.must.iter().any(|c| *c == in_guild(GUILD)));
+    }
+
+    #[test]
+    fn midterm_guild_only_recalls_the_same_guild() {
+        let filter = midterm_filter(USER, MemoryScope::Guild(GUILD));
+
+        assert_eq!(filter.must, vec![user(), in_guild(GUILD)]);
+        assert_ne!(filter, midterm_filter(USER, MemoryScope::Guild(GUILD + 1)));
+    }
+
+    #[test]
+    fn longterm_direct_messages_skip_legacy_facts() {
+        let filter = longterm_filter(USER, MemoryScope::DirectMessage);
+
+        // `is_empty` はキーの無い古い事実にも一致してしまう
+        assert_eq!(filter.must, vec![user(), Condition::is_null("guild_id")]);
+        assert!(!filter.must.contains(&Condition::is_empty("guild_id")));
+    }
+
+    #[test]
+    fn longterm_guild_only_recalls_the_same_guild() {
+        let filter = longterm_filter(USER, MemoryScope::Guild(GUILD));
+
+        assert_eq!(filter.must, vec![user(), in_guild(GUILD)]);
+    }
+
+    #[test]
+    fn legacy_migration_leaves_direct_message_memories_alone() {
+        let midterm = legacy_midterm_filter();
+        assert!(
+            midterm
+                .must_not
+                .contains(&Condition::matches("direct_message", true))
+        );
+
+        let longterm = legacy_longterm_filter();
+        assert_eq!(longterm.must, vec![Condition::is_empty("guild_id")]);
+        assert_eq!(longterm.must_not, vec![Condition::is_null("guild_id")]);
+    }
+
+    #[test]
+    fn direct_message_facts_are_stored_with_a_null_guild() {
+        let memory = LongTermMemory {
+            id: "id".to_string(),
+            user_id: USER,
+            fact: "fact".to_string(),
+            category: "general".to_string(),
+            created_at: 0,
+            updated_at: 0,
+            guild_id: None,
+        };
+
+        // `legacy_longterm_filter` と `longterm_filter` はこの `null` で新旧を見分ける
+        let payload = serde_json::to_value(&memory).unwrap();
+        assert_eq!(payload.get("guild_id"), Some(&serde_json::Value::Null));
+    }
 }
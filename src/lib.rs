@@ -4,70 +4,400 @@ pub mod infrastructure;
 pub mod models;
 pub mod presentation;
 pub mod shared;
+#[cfg(test)]
+mod test_support;
 
-use std::sync::Arc;
+use std::{path::Path, sync::Arc, time::Instant};
 
 use anyhow::{Context, Result};
-use application::traits::{
-    ai_client::AIClient, long_term_store::LongTermStore, short_term_store::ShortTermStore,
+use application::{
+    chat::{chat_service::current_timestamp, promotion_service::retry_due_promotions},
+    feedback::feedback_service::instruction_version,
+    health::health_service::HealthChecker,
+    traits::{
+        ai_client::AIClient, feedback_store::FeedbackStore, long_term_store::LongTermStore,
+        promotion_queue::PromotionQueue, settings_store::SettingsStore,
+        short_term_store::ShortTermStore,
+    },
 };
 use infrastructure::{
     ai::rig_client::RigClient,
     discord::client::DiscordClient,
-    store::{in_memory_store::InMemoryStore, vector_store::VectorStore},
+    resilience::{
+        circuit_breaker::CircuitBreaker, circuit_breaker_ai_client::CircuitBreakerAIClient,
+        circuit_breaker_store::CircuitBreakerStore,
+    },
+    store::{
+        file_feedback_store::FileFeedbackStore, file_promotion_queue::FilePromotionQueue,
+        file_settings_store::FileSettingsStore, in_memory_store::InMemoryStore,
+        short_term_snapshot, vector_store::VectorStore,
+    },
 };
-use shared::config::Config;
-use tokio::time::{Duration, interval};
+use presentation::{
+    auto_response::AutoResponseCooldowns,
+    command::command_registry::Data,
+    direct_message::DmMembership,
+    http::{chat_api_server, health_server, metrics_server},
+    reply_tracker::ReplyTracker,
+};
+use serenity::all::ShardManager;
+use shared::{
+    config::{ChatApi, Config, Embedding, PromotionRetry, Shutdown},
+    metrics::metrics,
+    secret::Secret,
+    shutdown::{InFlight, shutdown_signal},
+};
+use tokio::{
+    task::JoinHandle,
+    time::{Duration, Instant as Deadline, interval, timeout_at},
+};
+
+/// 埋め込みモデルの次元数を確かめるために送る文字列
+const EMBEDDING_PROBE: &str = "dimension probe";
 
 pub struct Application {
     discord_client: DiscordClient,
+    data: Arc<Data>,
+    shutdown: Shutdown,
+    /// 終了時に止める、定期処理とHTTPサーバーのタスク
+    background_tasks: Vec<JoinHandle<()>>,
 }
 
-impl Application {
-    pub async fn new(config: Config) -> Result<Self> {
-        let ai_client: Arc<dyn AIClient> = Arc::new(
-            RigClient::new(
-                config.nlp_token.clone(),
-                config.embed_token.clone(),
-                config.nlp.clone(),
-                config.embedding.clone(),
-            )
-            .await?,
+/// Discordに依存しない、AIと記憶の構成。CLIからも使う
+pub struct Services {
+    pub ai_client: Arc<dyn AIClient>,
+    pub short_term_store: Arc<dyn ShortTermStore>,
+    pub long_term_store: Arc<dyn LongTermStore>,
+    pub promotion_queue: Arc<dyn PromotionQueue>,
+    /// 起動時の確認やヘルスチェックで、サーキットブレーカーを通さずに使う
+    rig_client: Arc<RigClient>,
+    vector_store: Arc<VectorStore>,
+}
+
+impl Services {
+    /// ボット用。失敗した昇格の再試行キューをファイルに保存する
+    async fn new(config: &Config) -> Result<Self> {
+        let promotion_queue = Arc::new(
+            FilePromotionQueue::new(&config.promotion_retry.queue_path)
+                .await
+                .context("Failed to load promotion retry queue")?,
         );
+        Self::with_promotion_queue(config, promotion_queue).await
+    }
+
+    /// CLI用。再試行キューは、動いているボットのファイルを書き換えないようメモリにだけ置く
+    pub async fn for_cli(config: &Config) -> Result<Self> {
+        Self::with_promotion_queue(config, Arc::new(FilePromotionQueue::in_memory())).await
+    }
+
+    async fn with_promotion_queue(
+        config: &Config,
+        promotion_queue: Arc<dyn PromotionQueue>,
+    ) -> Result<Self> {
+        let rig_client = RigClient::new(
+            config.nlp_token.clone(),
+            config.embed_token.clone(),
+            config.nlp.clone(),
+            config.embedding.clone(),
+        )
+        .await?;
+        let rig_client = Arc::new(rig_client);
+
+        let embed_breaker = Arc::new(CircuitBreaker::new("embedding", &config.circuit_breaker));
+        let ai_client: Arc<dyn AIClient> = Arc::new(CircuitBreakerAIClient::new(
+            rig_client.clone(),
+            embed_breaker,
+        ));
 
         let short_term_store: Arc<dyn ShortTermStore> =
             Arc::new(InMemoryStore::new(config.nlp.max_short_term_messages));
-        let long_term_store: Arc<dyn LongTermStore> = Arc::new(
+
+        let vector_store = Arc::new(
             VectorStore::new(&config.qdrant_url, config.embedding.dimension)
+                .context("Failed to create Qdrant client")?,
+        );
+        if let Err(err) = vector_store.ensure_collections().await {
+            tracing::warn!(
+                "Qdrant is unavailable at startup; running without mid/long-term memory until it recovers: {err}"
+            );
+        }
+        let qdrant_breaker = Arc::new(CircuitBreaker::new("qdrant", &config.circuit_breaker));
+        let long_term_store: Arc<dyn LongTermStore> = Arc::new(CircuitBreakerStore::new(
+            vector_store.clone(),
+            qdrant_breaker,
+        ));
+
+        Ok(Self {
+            ai_client,
+            short_term_store,
+            long_term_store,
+            promotion_queue,
+            rig_client,
+            vector_store,
+        })
+    }
+
+    /// 次元数の不一致はQdrantへの保存が全て失敗するので起動を止める。APIに届かないだけなら続行する
+    async fn verify_embedding_dimension(&self, embedding: &Embedding) -> Result<()> {
+        match self.rig_client.embed(EMBEDDING_PROBE.to_string()).await {
+            Ok(vector) => ensure_embedding_dimension(embedding, vector.len())?,
+            Err(err) => {
+                tracing::warn!("Could not verify embedding.dimension at startup: {err}")
+            }
+        }
+        Ok(())
+    }
+
+    /// サーバーの記録がない以前の記憶を、`guild_id` のサーバーのものとして書き換える
+    async fn assign_legacy_guild(&self, guild_id: u64) {
+        if let Err(err) = self.vector_store.assign_legacy_guild(guild_id).await {
+            tracing::warn!("Failed to assign legacy memories to guild {guild_id}: {err}");
+        }
+    }
+}
+
+impl Application {
+    pub async fn new(config: Config) -> Result<Self> {
+        config.validate_discord()?;
+        let services = Services::new(&config).await?;
+        // 課金される埋め込みの呼び出しと記憶の書き換えは、ボットの起動時だけ行う
+        services
+            .verify_embedding_dimension(&config.embedding)
+            .await?;
+        if let Some(guild_id) = config.guild_id {
+            services.assign_legacy_guild(guild_id).await;
+        }
+        let health_checker = HealthChecker::new(
+            services.rig_client.clone(),
+            services.vector_store.clone(),
+            Duration::from_secs(config.health_server.cache_secs),
+        );
+        let Services {
+            ai_client,
+            short_term_store,
+            long_term_store,
+            promotion_queue,
+            ..
+        } = services;
+
+        let settings_store: Arc<dyn SettingsStore> = Arc::new(
+            FileSettingsStore::new(&config.guild_settings.path, &config.user_settings.path)
                 .await
-                .context("Failed to connect to Qdrant")?,
+                .context("Failed to load guild settings")?,
         );
 
-        spawn_cleanup_task(long_term_store.clone());
+        let feedback_store: Arc<dyn FeedbackStore> = Arc::new(
+            FileFeedbackStore::new(&config.feedback.path)
+                .await
+                .context("Failed to load feedback")?,
+        );
+        let instruction =
+            std::fs::read_to_string("INSTRUCTION.md").context("Failed to read INSTRUCTION.md")?;
+
+        let snapshot_path = Path::new(&config.shutdown.short_term_path);
+        match short_term_snapshot::restore(snapshot_path, short_term_store.as_ref()).await {
+            Ok(0) => {}
+            Ok(messages) => {
+                tracing::info!(messages, "Restored short-term memory from last shutdown")
+            }
+            Err(err) => tracing::warn!("Failed to restore short-term memory: {err:#}"),
+        }
+
+        let mut background_tasks = vec![
+            spawn_cleanup_task(long_term_store.clone()),
+            spawn_promotion_retry_task(
+                ai_client.clone(),
+                long_term_store.clone(),
+                promotion_queue.clone(),
+                config.promotion_retry.clone(),
+            ),
+        ];
 
-        let discord_client = DiscordClient::new(
-            config.discord_token.clone(),
-            config.guild_id,
+        let data = Arc::new(Data {
             ai_client,
             short_term_store,
             long_term_store,
+            promotion_queue,
+            mentions: config.mentions.clone(),
+            attachments: config.attachments.clone(),
+            guild_ids: config.guild_ids(),
+            guilds: config.guilds.clone(),
+            direct_messages: config.direct_messages.clone(),
+            dm_membership: DmMembership::default(),
+            settings_store,
+            auto_response_cooldowns: AutoResponseCooldowns::new(&config.auto_response),
+            reply_tracker: ReplyTracker::default(),
+            feedback_store,
+            model_name: config.nlp.model_name.clone(),
+            instruction_version: instruction_version(&instruction),
+            started_at: Instant::now(),
+            health_checker,
+            in_flight: Arc::new(InFlight::default()),
+        });
+        let discord_client =
+            DiscordClient::new(config.discord_token.expose().to_string(), data.clone()).await?;
+
+        if config.health_server.enabled {
+            background_tasks.push(spawn_health_server(
+                config.health_server.address.clone(),
+                data.clone(),
+                discord_client.shard_manager(),
+            ));
+        }
+        if config.metrics_server.enabled {
+            background_tasks.push(spawn_metrics_server(
+                config.metrics_server.address.clone(),
+                data.clone(),
+            ));
+        }
+        if config.chat_api.enabled {
+            background_tasks.push(spawn_chat_api_server(
+                config.chat_api.clone(),
+                config.chat_api_keys()?,
+                data.clone(),
+            ));
+        }
+
+        Ok(Self {
+            discord_client,
+            data,
+            shutdown: config.shutdown,
+            background_tasks,
+        })
+    }
+
+    /// `--check-config` 用。読み込み時の検証に加えて、埋め込みモデルを実際に呼び出して次元数を確かめる
+    pub async fn check_config(config: &Config) -> Result<()> {
+        config.validate_discord()?;
+        let rig_client = RigClient::new(
+            config.nlp_token.clone(),
+            config.embed_token.clone(),
+            config.nlp.clone(),
+            config.embedding.clone(),
         )
         .await?;
+        let vector = rig_client
+            .embed(EMBEDDING_PROBE.to_string())
+            .await
+            .context("Failed to call the embedding model")?;
 
-        Ok(Self { discord_client })
+        ensure_embedding_dimension(&config.embedding, vector.len())
     }
 
+    /// SIGTERMかCtrl+Cを受け取ったら、`shutdown.timeout_secs` 以内に後始末をして戻る
     pub async fn run(self) -> Result<()> {
-        self.discord_client
-            .run()
+        let Self {
+            discord_client,
+            data,
+            shutdown,
+            background_tasks,
+        } = self;
+        let shard_manager = discord_client.shard_manager();
+        let mut client_task = tokio::spawn(discord_client.run());
+
+        tokio::select! {
+            result = &mut client_task => {
+                return result
+                    .context("Discord client task panicked")?
+                    .context("Failed to run Discord client");
+            }
+            _ = shutdown_signal() => {}
+        }
+
+        let deadline = Deadline::now() + Duration::from_secs(shutdown.timeout_secs);
+        tracing::info!(
+            active = data.in_flight.active(),
+            timeout_secs = shutdown.timeout_secs,
+            "Shutting down; waiting for in-flight work"
+        );
+
+        // 新しいメッセージとコマンドを断り、生成中の応答を待つ
+        data.in_flight.close();
+        if timeout_at(deadline, data.in_flight.wait_idle())
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                active = data.in_flight.active(),
+                "Shutdown deadline reached with work still in flight"
+            );
+        }
+
+        for task in &background_tasks {
+            task.abort();
+        }
+
+        // 待ち切れなかった処理があっても、その時点までの会話は残す
+        let snapshot_path = Path::new(&shutdown.short_term_path);
+        match short_term_snapshot::save(snapshot_path, data.short_term_store.as_ref()).await {
+            Ok(messages) => tracing::info!(messages, "Saved short-term memory"),
+            Err(err) => tracing::error!("Failed to save short-term memory: {err:#}"),
+        }
+
+        if timeout_at(deadline, shard_manager.shutdown_all())
             .await
-            .context("Failed to run Discord client")?;
+            .is_err()
+        {
+            tracing::warn!("Shutdown deadline reached before the Discord shards stopped");
+            client_task.abort();
+            return Ok(());
+        }
+        match timeout_at(deadline, client_task).await {
+            Ok(Ok(Err(err))) => tracing::warn!("Discord client stopped with an error: {err:#}"),
+            Ok(Err(err)) => tracing::warn!("Discord client task failed: {err}"),
+            Ok(Ok(Ok(()))) => {}
+            Err(_) => tracing::warn!("Shutdown deadline reached before the Discord client stopped"),
+        }
 
+        tracing::info!("Shutdown complete");
         Ok(())
     }
 }
 
-fn spawn_cleanup_task(long_term_store: Arc<dyn LongTermStore>) {
+fn ensure_embedding_dimension(embedding: &Embedding, actual: usize) -> Result<()> {
+    if actual as u64 != embedding.dimension {
+        anyhow::bail!(
+            "embedding.dimension is {} but {} returns {actual}-dimensional vectors",
+            embedding.dimension,
+            embedding.model_name
+        );
+    }
+    Ok(())
+}
+
+fn spawn_health_server(
+    address: String,
+    data: Arc<Data>,
+    shard_manager: Arc<ShardManager>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(err) = health_server::serve(&address, data, shard_manager).await {
+            tracing::error!("Health server failed: {err:#}");
+        }
+    })
+}
+
+fn spawn_metrics_server(address: String, data: Arc<Data>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(err) = metrics_server::serve(&address, data).await {
+            tracing::error!("Metrics server failed: {err:#}");
+        }
+    })
+}
+
+fn spawn_chat_api_server(
+    chat_api: ChatApi,
+    keys: Vec<(Secret, u64)>,
+    data: Arc<Data>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(err) = chat_api_server::serve(&chat_api, keys, data).await {
+            tracing::error!("Chat API server failed: {err:#}");
+        }
+    })
+}
+
+fn spawn_cleanup_task(long_term_store: Arc<dyn LongTermStore>) -> JoinHandle<()> {
     tokio::spawn(async move {
         let mut ticker = interval(Duration::from_secs(60 * 60)); // 1時間ごと
         loop {
@@ -76,5 +406,38 @@ fn spawn_cleanup_task(long_term_store: Arc<dyn LongTermStore>) {
                 tracing::warn!("Failed to cleanup expired midterm memories: {err}");
             }
         }
-    });
+    })
+}
+
+fn spawn_promotion_retry_task(
+    ai_client: Arc<dyn AIClient>,
+    long_term_store: Arc<dyn LongTermStore>,
+    promotion_queue: Arc<dyn PromotionQueue>,
+    retry: PromotionRetry,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(retry.interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            let report = retry_due_promotions(
+                ai_client.as_ref(),
+                long_term_store.as_ref(),
+                promotion_queue.as_ref(),
+                &retry,
+                current_timestamp(),
+            )
+            .await;
+
+            if report.succeeded + report.rescheduled + report.dropped > 0 {
+                tracing::info!(
+                    succeeded = report.succeeded,
+                    rescheduled = report.rescheduled,
+                    dropped = report.dropped,
+                    queue_depth = metrics().promotion_queue_depth.get(),
+                    permanent_failures = metrics().promotion_failures(true),
+                    "Retried pending midterm promotions"
+                );
+            }
+        }
+    })
 }
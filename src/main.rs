@@ -1,18 +1,63 @@
 use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use neko_ai::presentation::cli::{
+    admin::{self, AdminCommand},
+    repl::{self, ReplArgs},
+};
 use tracing::debug;
 
+#[derive(Debug, Parser)]
+#[command(version, about = "NekoAI - 長期記憶を持つDiscord用AIチャットボット")]
+struct Cli {
+    /// 設定を検証して終了する（埋め込みモデルに問い合わせて次元数も確かめる）
+    #[arg(long)]
+    check_config: bool,
+
+    /// 省略するとボットを起動する
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Admin(AdminCommand),
+    /// Discordを介さずに端末で会話する（INSTRUCTION.md や記憶の確認用）
+    Repl(ReplArgs),
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("NekoAI (Ver. 0.0.2-alpha)\n");
+    let cli = Cli::parse();
+
+    // サブコマンドの出力（`export-memories` のJSONなど）に混ざらないようにする
+    if cli.command.is_none() {
+        println!("NekoAI (Ver. 0.0.2-alpha)\n");
+    }
 
     let config = neko_ai::shared::config::Config::load().context("Failed to load config")?;
 
-    neko_ai::shared::logger::init_tracing(&config.log_level);
+    let _tracing_guard = neko_ai::shared::logger::init_tracing(&config.log_level, &config.logging)
+        .context("Failed to initialize tracing")?;
 
     debug!("----------BEGIN SETTINGS----------");
     debug!("{:#?}", &config);
     debug!("----------END SETTINGS----------");
 
+    if cli.check_config {
+        neko_ai::Application::check_config(&config)
+            .await
+            .context("Configuration check failed")?;
+        println!("Configuration OK");
+        return Ok(());
+    }
+
+    match cli.command {
+        Some(Command::Admin(command)) => return admin::run(command, &config).await,
+        Some(Command::Repl(args)) => return repl::run(args, &config).await,
+        None => {}
+    }
+
     neko_ai::Application::new(config)
         .await?
         .run()
@@ -27,17 +27,23 @@ pub enum AppError {
     Internal(#[from] anyhow::Error),
 }
 
+/// 依存先が停止中と判断され、呼び出しが行われなかったことを表す
+#[derive(Debug, Error)]
+#[error("{0} is unavailable (circuit open)")]
+pub struct CircuitOpen(pub &'static str);
+
 impl AppError {
-    pub fn user_facing_message(&self) -> &str {
+    /// メトリクスのラベルに使う、バリアントの名前
+    pub fn kind(&self) -> &'static str {
         match self {
-            AppError::AIGeneration(_) => "AI応答の生成に失敗しました。",
-            AppError::Embedding(_) => "テキストの処理に失敗しました。",
-            AppError::Store(_) => "記憶の検索に失敗しました。",
-            AppError::Discord(_) => "Discordとの通信に失敗しました。",
-            AppError::Config(_) => "設定の読み込みに失敗しました。",
-            AppError::ConversationNotFound(_) => "会話が見つかりませんでした。",
-            AppError::PermissionDenied { .. } => "権限がありません。",
-            AppError::Internal(_) => "予期しないエラーが発生しました。",
+            AppError::AIGeneration(_) => "AIGeneration",
+            AppError::Embedding(_) => "Embedding",
+            AppError::Store(_) => "Store",
+            AppError::Discord(_) => "Discord",
+            AppError::Config(_) => "Config",
+            AppError::ConversationNotFound(_) => "ConversationNotFound",
+            AppError::PermissionDenied { .. } => "PermissionDenied",
+            AppError::Internal(_) => "Internal",
         }
     }
 }
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Good,
+    Bad,
+}
+
+/// 応答に対する1人分の評価と、その応答を生成した時の入力・出力
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feedback {
+    pub turn_id: u64,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    /// 質問したユーザー
+    pub requester_id: u64,
+    /// 評価したユーザー
+    pub rater_id: u64,
+    pub rating: Rating,
+    pub prompt: String,
+    pub memories: Vec<String>,
+    pub model: String,
+    /// 生成時のシステムプロンプト（INSTRUCTION.md）の版
+    pub instruction_version: String,
+    pub response: String,
+    pub rated_at: i64,
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// 依存先ごとの確認結果
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub healthy: bool,
+    /// 応答までにかかった時間。タイムアウトした場合は待った時間
+    #[serde(rename = "latency_ms", serialize_with = "as_millis")]
+    pub latency: Duration,
+    /// 正常時は補足情報、異常時はエラーの内容
+    pub detail: String,
+}
+
+/// Qdrantのコレクションの状態
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionStats {
+    pub name: String,
+    pub status: String,
+    pub points: u64,
+}
+
+/// 短期記憶に保持している会話の量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ShortTermStats {
+    pub channels: usize,
+    pub messages: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub checks: Vec<CheckResult>,
+    pub short_term: ShortTermStats,
+    #[serde(rename = "uptime_secs", serialize_with = "as_secs")]
+    pub uptime: Duration,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.healthy)
+    }
+}
+
+fn as_millis<S: serde::Serializer>(latency: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u64(latency.as_millis() as u64)
+}
+
+fn as_secs<S: serde::Serializer>(uptime: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u64(uptime.as_secs())
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+/// ボットが生成する文言の言語。モデルの応答の言語はシステムプロンプトに従う
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+impl Locale {
+    /// Discordのロケール（`ja`・`en-US` など）から選ぶ。対応していない言語は `None`
+    pub fn from_discord(locale: &str) -> Option<Self> {
+        let language = locale.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "ja" => Some(Self::Ja),
+            "en" => Some(Self::En),
+            _ => None,
+        }
+    }
+
+    /// 利用者自身の設定、インタラクションのロケール、サーバーの優先ロケールの順に選ぶ
+    pub fn resolve(
+        preferred: Option<Self>,
+        interaction: Option<&str>,
+        guild: Option<&str>,
+    ) -> Self {
+        preferred
+            .or_else(|| interaction.and_then(Self::from_discord))
+            .or_else(|| guild.and_then(Self::from_discord))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_discord_locales() {
+        assert_eq!(Locale::from_discord("ja"), Some(Locale::Ja));
+        assert_eq!(Locale::from_discord("en-US"), Some(Locale::En));
+        assert_eq!(Locale::from_discord("en-GB"), Some(Locale::En));
+        assert_eq!(Locale::from_discord("fr"), None);
+    }
+
+    #[test]
+    fn user_setting_wins_over_discord_locales() {
+        assert_eq!(
+            Locale::resolve(Some(Locale::Ja), Some("en-US"), Some("en-US")),
+            Locale::Ja
+        );
+        assert_eq!(Locale::resolve(None, Some("en-US"), Some("ja")), Locale::En);
+        assert_eq!(Locale::resolve(None, Some("fr"), Some("en-GB")), Locale::En);
+        assert_eq!(Locale::resolve(None, None, None), Locale::Ja);
+    }
+}
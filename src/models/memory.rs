@@ -28,6 +28,20 @@ impl ChatMessage {
     }
 }
 
+/// 生成に使ったトークン数。プロバイダーが返さない場合は0
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// 生成した応答と、その生成に使ったトークン数
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub content: String,
+    pub usage: TokenUsage,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Role {
     User,
@@ -40,6 +54,32 @@ pub struct ShortTermMessage {
     pub user_id: u64,
     pub content: String,
     pub timestamp: i64,
+    /// 発言の元になったDiscordのメッセージ（またはインタラクション）のID。再生成時に同じターンを置き換えるのに使う
+    #[serde(default)]
+    pub turn_id: u64,
+}
+
+/// 記憶を検索する範囲。サーバーでの会話はそのサーバーの中で、DMでの会話はDMの中でしか思い出さない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryScope {
+    Guild(u64),
+    DirectMessage,
+}
+
+impl MemoryScope {
+    pub fn for_guild(guild_id: Option<u64>) -> Self {
+        match guild_id {
+            Some(id) => MemoryScope::Guild(id),
+            None => MemoryScope::DirectMessage,
+        }
+    }
+
+    pub fn guild_id(&self) -> Option<u64> {
+        match self {
+            MemoryScope::Guild(id) => Some(*id),
+            MemoryScope::DirectMessage => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +90,14 @@ pub struct MidTermMemory {
     pub summary: String,
     pub created_at: i64,
     pub expires_at: i64,
+    #[serde(default)]
+    pub direct_message: bool,
+    /// 会話したサーバー。DMでは `None`
+    #[serde(default)]
+    pub guild_id: Option<u64>,
+    /// 昇格元の短期記憶のターン。応答の削除時に一緒に消すのに使う
+    #[serde(default)]
+    pub turn_id: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,4 +108,41 @@ pub struct LongTermMemory {
     pub category: String,
     pub created_at: i64,
     pub updated_at: i64,
+    /// 事実を知ったサーバー。DMでは `None`
+    #[serde(default)]
+    pub guild_id: Option<u64>,
+}
+
+/// 書き出し・取り込みに使う記憶の一覧
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryExport {
+    #[serde(default)]
+    pub midterm: Vec<MidTermMemory>,
+    #[serde(default)]
+    pub longterm: Vec<LongTermMemory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingPromotion {
+    pub memory: MidTermMemory,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub last_error: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_follows_where_the_conversation_happened() {
+        assert_eq!(MemoryScope::for_guild(Some(7)), MemoryScope::Guild(7));
+        assert_eq!(MemoryScope::for_guild(None), MemoryScope::DirectMessage);
+    }
+
+    #[test]
+    fn scope_round_trips_the_guild_id() {
+        assert_eq!(MemoryScope::Guild(7).guild_id(), Some(7));
+        assert_eq!(MemoryScope::DirectMessage.guild_id(), None);
+    }
 }
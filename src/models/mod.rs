@@ -1,2 +1,6 @@
 pub mod error;
+pub mod feedback;
+pub mod health;
+pub mod locale;
 pub mod memory;
+pub mod settings;
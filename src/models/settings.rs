@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::locale::Locale;
+
+/// チャンネルでの応答方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelMode {
+    /// メンションされた時だけ応答する
+    #[default]
+    MentionOnly,
+    /// メンションが無くても全ての発言に応答する
+    AutoRespond,
+}
+
+/// アクセス制御の対象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessTarget {
+    User(u64),
+    Role(u64),
+    Channel(u64),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessList {
+    pub users: Vec<u64>,
+    pub roles: Vec<u64>,
+    /// カテゴリーを指定すると、その中のチャンネルとスレッドも対象になる
+    pub channels: Vec<u64>,
+}
+
+impl AccessList {
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.roles.is_empty() && self.channels.is_empty()
+    }
+
+    pub fn insert(&mut self, target: AccessTarget) {
+        let (ids, id) = self.ids_mut(target);
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    pub fn remove(&mut self, target: AccessTarget) {
+        let (ids, id) = self.ids_mut(target);
+        ids.retain(|existing| *existing != id);
+    }
+
+    fn ids_mut(&mut self, target: AccessTarget) -> (&mut Vec<u64>, u64) {
+        match target {
+            AccessTarget::User(id) => (&mut self.users, id),
+            AccessTarget::Role(id) => (&mut self.roles, id),
+            AccessTarget::Channel(id) => (&mut self.channels, id),
+        }
+    }
+}
+
+/// 誰がどのチャンネルでボットを使えるか
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessRules {
+    /// 空でない項目があれば、それに一致する場合だけ利用できる
+    pub allow: AccessList,
+    /// 許可より優先される
+    pub deny: AccessList,
+    /// Discordの権限が無くても管理コマンドを使えるロール
+    pub admin_roles: Vec<u64>,
+}
+
+/// 管理者がボットを黙らせた対象。どちらも理由を返さずに無視する
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Moderation {
+    pub blocked_users: Vec<u64>,
+    /// カテゴリーを指定すると、その中のチャンネルとスレッドも対象になる
+    pub muted_channels: Vec<u64>,
+}
+
+impl Moderation {
+    pub fn is_blocked(&self, user_id: u64) -> bool {
+        self.blocked_users.contains(&user_id)
+    }
+
+    pub fn is_muted(&self, channel_ids: &[u64]) -> bool {
+        channel_ids
+            .iter()
+            .any(|id| self.muted_channels.contains(id))
+    }
+
+    /// 状態が変わった場合は `true`
+    pub fn set_blocked(&mut self, user_id: u64, blocked: bool) -> bool {
+        set_member(&mut self.blocked_users, user_id, blocked)
+    }
+
+    /// 状態が変わった場合は `true`
+    pub fn set_muted(&mut self, channel_id: u64, muted: bool) -> bool {
+        set_member(&mut self.muted_channels, channel_id, muted)
+    }
+}
+
+fn set_member(ids: &mut Vec<u64>, id: u64, present: bool) -> bool {
+    let was_present = ids.contains(&id);
+    ids.retain(|existing| *existing != id);
+    if present {
+        ids.push(id);
+    }
+    was_present != present
+}
+
+/// 管理コマンドで変更される、サーバーごとの設定
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    pub channel_modes: HashMap<u64, ChannelMode>,
+    pub access: AccessRules,
+    pub moderation: Moderation,
+}
+
+impl GuildSettings {
+    pub fn channel_mode(&self, channel_id: u64) -> ChannelMode {
+        self.channel_modes
+            .get(&channel_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 既定値に戻す場合は項目ごと消し、ファイルに残さない
+    pub fn set_channel_mode(&mut self, channel_id: u64, mode: ChannelMode) {
+        if mode == ChannelMode::default() {
+            self.channel_modes.remove(&channel_id);
+        } else {
+            self.channel_modes.insert(channel_id, mode);
+        }
+    }
+}
+
+/// 利用者が自分で変更する、サーバーをまたいだ設定
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// `None` ならDiscordのロケールに従う
+    pub locale: Option<Locale>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_blocked_does_not_duplicate() {
+        let mut moderation = Moderation::default();
+
+        assert!(moderation.set_blocked(1, true));
+        assert!(!moderation.set_blocked(1, true));
+        assert!(moderation.is_blocked(1));
+        assert_eq!(moderation.blocked_users, vec![1]);
+
+        assert!(moderation.set_blocked(1, false));
+        assert!(!moderation.set_blocked(1, false));
+        assert!(!moderation.is_blocked(1));
+    }
+
+    #[test]
+    fn muting_a_parent_mutes_its_children() {
+        let mut moderation = Moderation::default();
+        moderation.set_muted(10, true);
+
+        // スレッド → チャンネル → カテゴリー
+        assert!(moderation.is_muted(&[30, 20, 10]));
+        assert!(!moderation.is_muted(&[30, 20]));
+    }
+}
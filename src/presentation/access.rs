@@ -0,0 +1,142 @@
+use serenity::all::{ChannelId, Context, GuildId, PartialMember, UserId};
+
+use crate::{
+    application::access::access_service::{AccessRequest, authorize, is_bot_admin},
+    models::error::AppError,
+    presentation::{command::command_registry::Data, direct_message::dm_guilds},
+};
+
+/// 親を辿る最大段数。スレッド → チャンネル → カテゴリー
+const MAX_CHANNEL_DEPTH: usize = 3;
+
+/// サーバーのアクセス制御ルールに照らして、ボットを使えるかを判定する。DMでは常に許可。
+/// メッセージイベントでは `Message::member` を渡すと、APIに問い合わせずにそのロールで判定する
+pub async fn check_access(
+    ctx: &Context,
+    data: &Data,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    user_id: UserId,
+    member: Option<&PartialMember>,
+) -> Result<(), AppError> {
+    let Some(guild_id) = guild_id else {
+        return Ok(());
+    };
+
+    let rules = data.settings_store.guild(guild_id.get()).await.access;
+    let member = match member {
+        Some(member) => Some(partial_member_access(ctx, guild_id, user_id, member)),
+        None => member_access(ctx, guild_id, user_id).await,
+    };
+    let request = AccessRequest {
+        user_id: user_id.get(),
+        is_admin: member.as_ref().is_some_and(|member| {
+            is_bot_admin(&rules, member.has_admin_permission, &member.role_ids)
+        }),
+        role_ids: member.map(|member| member.role_ids),
+        channel_ids: channel_chain(ctx, guild_id, channel_id),
+    };
+    authorize(&rules, &request)
+}
+
+/// 管理者にブロックされたユーザーか、ミュートされたチャンネルなら `true`。
+/// DMでは、DMを許可するかの判定に使うサーバーのどれかでブロックされていれば `true`
+pub async fn is_silenced(
+    ctx: &Context,
+    data: &Data,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    user_id: UserId,
+) -> bool {
+    let Some(guild_id) = guild_id else {
+        for guild_id in dm_guilds(ctx, data) {
+            let moderation = data.settings_store.guild(guild_id.get()).await.moderation;
+            if moderation.is_blocked(user_id.get()) {
+                return true;
+            }
+        }
+        return false;
+    };
+
+    let moderation = data.settings_store.guild(guild_id.get()).await.moderation;
+    moderation.is_blocked(user_id.get())
+        || moderation.is_muted(&channel_chain(ctx, guild_id, channel_id))
+}
+
+/// 管理コマンドを使えるか。Discordの管理者・サーバー管理権限か、ボット管理者ロールが必要
+pub async fn is_admin(ctx: &Context, data: &Data, guild_id: GuildId, user_id: UserId) -> bool {
+    let rules = data.settings_store.guild(guild_id.get()).await.access;
+    member_access(ctx, guild_id, user_id)
+        .await
+        .is_some_and(|member| is_bot_admin(&rules, member.has_admin_permission, &member.role_ids))
+}
+
+struct MemberAccess {
+    role_ids: Vec<u64>,
+    has_admin_permission: bool,
+}
+
+/// メンバーを取得できなければ `None`
+async fn member_access(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Option<MemberAccess> {
+    let member = match guild_id.member(ctx, user_id).await {
+        Ok(member) => member,
+        Err(err) => {
+            tracing::warn!(%guild_id, %user_id, "Failed to fetch member for access check: {err}");
+            return None;
+        }
+    };
+
+    let has_admin_permission = ctx.cache.guild(guild_id).is_some_and(|guild| {
+        let permissions = guild.member_permissions(&member);
+        permissions.administrator() || permissions.manage_guild()
+    });
+
+    Some(MemberAccess {
+        role_ids: member.roles.iter().map(|r| r.get()).collect(),
+        has_admin_permission,
+    })
+}
+
+fn partial_member_access(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    member: &PartialMember,
+) -> MemberAccess {
+    let has_admin_permission = ctx.cache.guild(guild_id).is_some_and(|guild| {
+        let permissions = guild.partial_member_permissions(user_id, member);
+        permissions.administrator() || permissions.manage_guild()
+    });
+
+    MemberAccess {
+        role_ids: member.roles.iter().map(|r| r.get()).collect(),
+        has_admin_permission,
+    }
+}
+
+/// チャンネルと、その親（スレッドの親チャンネルやカテゴリー）のID
+fn channel_chain(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Vec<u64> {
+    let Some(guild) = ctx.cache.guild(guild_id) else {
+        return vec![channel_id.get()];
+    };
+
+    let mut channel_ids = Vec::new();
+    let mut current = Some(channel_id);
+    while let Some(id) = current.take()
+        && channel_ids.len() < MAX_CHANNEL_DEPTH
+    {
+        channel_ids.push(id.get());
+        current = guild
+            .channels
+            .get(&id)
+            .and_then(|c| c.parent_id)
+            .or_else(|| {
+                guild
+                    .threads
+                    .iter()
+                    .find(|t| t.id == id)
+                    .and_then(|t| t.parent_id)
+            });
+    }
+    channel_ids
+}
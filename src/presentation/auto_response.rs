@@ -0,0 +1,181 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serenity::all::{Message, UserId};
+
+use crate::{
+    models::settings::ChannelMode,
+    presentation::command::command_registry::{COMMAND_PREFIX, Data},
+    shared::config::AutoResponse,
+};
+
+/// これを超えたら期限切れの記録を掃除する
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// 自動応答チャンネルでの、チャンネル単位・ユーザー単位のクールダウン
+pub struct AutoResponseCooldowns {
+    channel_cooldown: Duration,
+    user_cooldown: Duration,
+    last_responses: Mutex<LastResponses>,
+}
+
+#[derive(Default)]
+struct LastResponses {
+    channels: HashMap<u64, Instant>,
+    users: HashMap<(u64, u64), Instant>,
+}
+
+impl AutoResponseCooldowns {
+    pub fn new(config: &AutoResponse) -> Self {
+        Self {
+            channel_cooldown: Duration::from_secs(config.channel_cooldown_secs),
+            user_cooldown: Duration::from_secs(config.user_cooldown_secs),
+            last_responses: Mutex::new(LastResponses::default()),
+        }
+    }
+
+    /// クールダウン中でなければ応答時刻を記録して `true` を返す
+    pub fn try_acquire(&self, channel_id: u64, user_id: u64) -> bool {
+        self.try_acquire_at(channel_id, user_id, Instant::now())
+    }
+
+    fn try_acquire_at(&self, channel_id: u64, user_id: u64, now: Instant) -> bool {
+        let mut last = self
+            .last_responses
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let cooling = |at: Option<&Instant>, cooldown: Duration| {
+            at.is_some_and(|at| now.duration_since(*at) < cooldown)
+        };
+        if cooling(last.channels.get(&channel_id), self.channel_cooldown)
+            || cooling(last.users.get(&(channel_id, user_id)), self.user_cooldown)
+        {
+            return false;
+        }
+
+        if last.channels.len() > PRUNE_THRESHOLD {
+            let cooldown = self.channel_cooldown;
+            last.channels
+                .retain(|_, at| now.duration_since(*at) < cooldown);
+        }
+        if last.users.len() > PRUNE_THRESHOLD {
+            let cooldown = self.user_cooldown;
+            last.users
+                .retain(|_, at| now.duration_since(*at) < cooldown);
+        }
+
+        last.channels.insert(channel_id, now);
+        last.users.insert((channel_id, user_id), now);
+        true
+    }
+}
+
+/// メンションの無い発言が、自動応答チャンネルで応答する対象かを判定する。クールダウンは
+/// アクセス制御を通った後に `acquire_cooldown` で取る
+pub async fn should_auto_respond(msg: &Message, data: &Data, bot_id: UserId) -> bool {
+    let Some(guild_id) = msg.guild_id else {
+        return false;
+    };
+
+    let content = msg.content.trim();
+    if content.is_empty() || content.starts_with(COMMAND_PREFIX) {
+        return false;
+    }
+
+    let settings = data.settings_store.guild(guild_id.get()).await;
+    if settings.channel_mode(msg.channel_id.get()) != ChannelMode::AutoRespond {
+        return false;
+    }
+
+    let referenced_author_id = msg
+        .referenced_message
+        .as_ref()
+        .map(|referenced| referenced.author.id.get());
+    let mentioned_user_ids: Vec<u64> = msg.mentions.iter().map(|u| u.id.get()).collect();
+    !addressed_to_others(referenced_author_id, &mentioned_user_ids, bot_id.get())
+}
+
+/// 実際に応答する発言だけがクールダウンを使うよう、アクセス制御の後に呼ぶ
+pub fn acquire_cooldown(msg: &Message, data: &Data) -> bool {
+    let acquired = data
+        .auto_response_cooldowns
+        .try_acquire(msg.channel_id.get(), msg.author.id.get());
+    if !acquired {
+        tracing::debug!(
+            channel_id = msg.channel_id.get(),
+            user_id = msg.author.id.get(),
+            "Skipped auto response during cooldown"
+        );
+    }
+    acquired
+}
+
+/// ボット以外への返信や、ボット以外だけを宛先にした発言はユーザー同士の会話とみなす
+pub fn addressed_to_others(
+    referenced_author_id: Option<u64>,
+    mentioned_user_ids: &[u64],
+    bot_id: u64,
+) -> bool {
+    if referenced_author_id.is_some_and(|id| id != bot_id) {
+        return true;
+    }
+    !mentioned_user_ids.is_empty() && !mentioned_user_ids.contains(&bot_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cooldowns(channel_secs: u64, user_secs: u64) -> AutoResponseCooldowns {
+        AutoResponseCooldowns::new(&AutoResponse {
+            channel_cooldown_secs: channel_secs,
+            user_cooldown_secs: user_secs,
+        })
+    }
+
+    #[test]
+    fn channel_cooldown_blocks_other_users() {
+        let cooldowns = cooldowns(3, 0);
+        let now = Instant::now();
+        assert!(cooldowns.try_acquire_at(1, 10, now));
+        assert!(!cooldowns.try_acquire_at(1, 20, now + Duration::from_secs(1)));
+        assert!(cooldowns.try_acquire_at(2, 20, now + Duration::from_secs(1)));
+        assert!(cooldowns.try_acquire_at(1, 20, now + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn user_cooldown_is_per_channel() {
+        let cooldowns = cooldowns(0, 10);
+        let now = Instant::now();
+        assert!(cooldowns.try_acquire_at(1, 10, now));
+        assert!(!cooldowns.try_acquire_at(1, 10, now + Duration::from_secs(5)));
+        assert!(cooldowns.try_acquire_at(1, 20, now + Duration::from_secs(5)));
+        assert!(cooldowns.try_acquire_at(2, 10, now + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn rejected_attempt_does_not_extend_cooldown() {
+        let cooldowns = cooldowns(0, 10);
+        let now = Instant::now();
+        assert!(cooldowns.try_acquire_at(1, 10, now));
+        assert!(!cooldowns.try_acquire_at(1, 10, now + Duration::from_secs(9)));
+        assert!(cooldowns.try_acquire_at(1, 10, now + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn replies_between_users_are_ignored() {
+        assert!(addressed_to_others(Some(2), &[], 9));
+        assert!(!addressed_to_others(Some(9), &[], 9));
+        assert!(!addressed_to_others(None, &[], 9));
+    }
+
+    #[test]
+    fn mentions_of_other_users_are_ignored() {
+        assert!(addressed_to_others(None, &[2], 9));
+        assert!(!addressed_to_others(None, &[2, 9], 9));
+    }
+}
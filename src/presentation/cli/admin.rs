@@ -0,0 +1,179 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+
+use crate::{
+    Services,
+    application::{
+        chat::chat_service::{ChatRequest, current_timestamp, process_message},
+        maintenance::maintenance_service::{StoreReport, import_memories, reembed_memories},
+    },
+    infrastructure::store::json_file,
+    models::memory::{MemoryExport, MemoryScope},
+    presentation::cli::wrap_cli_message,
+    shared::config::Config,
+};
+
+/// 運用のためのサブコマンド。Discordには接続しない
+#[derive(Debug, Subcommand)]
+pub enum AdminCommand {
+    /// ユーザーの中期・長期記憶をJSONで書き出す
+    ExportMemories {
+        #[arg(long)]
+        user: u64,
+        /// 書き出し先。省略すると標準出力
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// `export-memories` で書き出したJSONを、埋め込みを作り直して取り込む
+    ImportMemories { input: PathBuf },
+    /// 期限切れの中期記憶を今すぐ削除する
+    PurgeExpired,
+    /// Qdrantのコレクションの状態と件数を表示する
+    Stats,
+    /// 全ての記憶の埋め込みを今の埋め込みモデルで作り直す
+    Reembed {
+        /// コレクションを作り直す。次元数の違うモデルに移行する場合に指定する
+        #[arg(long)]
+        recreate: bool,
+        /// `--recreate` の前に全ての記憶を書き出す先。省略すると data/reembed_backup_{時刻}.json
+        #[arg(long)]
+        backup: Option<PathBuf>,
+    },
+    /// 1件の発言を `process_message` で処理し、応答と使った記憶を表示する
+    Prompt {
+        #[arg(long)]
+        user: u64,
+        /// 短期記憶のキー。省略するとユーザーIDを使う（DMと同じ扱い）
+        #[arg(long)]
+        channel: Option<u64>,
+        /// サーバーの記憶を使う場合に指定する。省略するとDMの記憶を使う
+        #[arg(long)]
+        guild: Option<u64>,
+        message: String,
+    },
+}
+
+pub async fn run(command: AdminCommand, config: &Config) -> Result<()> {
+    let services = Services::for_cli(config).await?;
+
+    match command {
+        AdminCommand::ExportMemories { user, output } => {
+            let memories = services
+                .long_term_store
+                .list_memories(Some(user))
+                .await
+                .context("Failed to read memories")?;
+            let json = serde_json::to_string_pretty(&memories)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!(
+                        "中期記憶 {}件、長期記憶 {}件を {} に書き出しました",
+                        memories.midterm.len(),
+                        memories.longterm.len(),
+                        path.display()
+                    );
+                }
+                None => println!("{json}"),
+            }
+        }
+        AdminCommand::ImportMemories { input } => {
+            let json = std::fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read {}", input.display()))?;
+            let memories: MemoryExport =
+                serde_json::from_str(&json).context("Failed to parse memory export")?;
+            let report = import_memories(
+                services.ai_client.as_ref(),
+                services.long_term_store.as_ref(),
+                memories,
+            )
+            .await?;
+            println!("{}を取り込みました", describe(report));
+        }
+        AdminCommand::PurgeExpired => {
+            services
+                .long_term_store
+                .delete_expired_midterm()
+                .await
+                .context("Failed to purge expired midterm memories")?;
+            println!("期限切れの中期記憶を削除しました");
+        }
+        AdminCommand::Stats => {
+            let stats = services
+                .long_term_store
+                .collection_stats()
+                .await
+                .context("Failed to read collection stats")?;
+            for collection in stats {
+                println!(
+                    "{}: {} ({}件)",
+                    collection.name, collection.status, collection.points
+                );
+            }
+        }
+        AdminCommand::Reembed { recreate, backup } => {
+            let backup = backup.unwrap_or_else(|| {
+                PathBuf::from(format!("data/reembed_backup_{}.json", current_timestamp()))
+            });
+            let report = reembed_memories(
+                services.ai_client.as_ref(),
+                services.long_term_store.as_ref(),
+                recreate,
+                async |memories: &MemoryExport| {
+                    json_file::save(&backup, memories).await?;
+                    eprintln!(
+                        "作り直す前の記憶を {} に書き出しました（失敗した場合は import-memories で戻せます）",
+                        backup.display()
+                    );
+                    Ok(())
+                },
+            )
+            .await?;
+            println!("{}の埋め込みを作り直しました", describe(report));
+        }
+        AdminCommand::Prompt {
+            user,
+            channel,
+            guild,
+            message,
+        } => {
+            let channel_id = channel.unwrap_or(user);
+            let scope = MemoryScope::for_guild(guild);
+            let response = process_message(
+                services.ai_client.as_ref(),
+                services.short_term_store.as_ref(),
+                services.long_term_store.as_ref(),
+                services.promotion_queue.as_ref(),
+                ChatRequest {
+                    channel_id,
+                    user_id: user,
+                    user_message: wrap_cli_message(user, channel_id, scope, &message),
+                    scope,
+                    turn_id: current_timestamp() as u64,
+                },
+            )
+            .await?;
+
+            if !response.memories.is_empty() {
+                eprintln!("--- 使った記憶 ---");
+                for memory in &response.memories {
+                    eprintln!("- {memory}");
+                }
+                eprintln!("------------------");
+            }
+            println!("{}", response.content);
+        }
+    }
+
+    Ok(())
+}
+
+fn describe(report: StoreReport) -> String {
+    format!(
+        "中期記憶 {}件、長期記憶 {}件",
+        report.midterm, report.longterm
+    )
+}
@@ -0,0 +1,43 @@
+pub mod admin;
+pub mod repl;
+
+use crate::{
+    application::chat::prompt_envelope::{MessageMetadata, wrap_user_message},
+    models::memory::MemoryScope,
+};
+
+/// ボットがDiscordの発言を包むのと同じ形にする。名前はDiscordから引けないのでIDで代える
+fn wrap_cli_message(user_id: u64, channel_id: u64, scope: MemoryScope, message: &str) -> String {
+    let (guild_name, guild_id) = match scope {
+        MemoryScope::Guild(guild_id) => (guild_id.to_string(), guild_id),
+        MemoryScope::DirectMessage => ("DM".to_string(), 0),
+    };
+    let metadata = MessageMetadata {
+        guild_name,
+        guild_id,
+        category_name: "None".to_string(),
+        channel_name: channel_id.to_string(),
+        channel_id,
+        user_name: format!("user-{user_id}"),
+        user_id,
+    };
+    wrap_user_message(&metadata, &[], message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_messages_use_the_bot_envelope() {
+        let wrapped = wrap_cli_message(2, 10, MemoryScope::Guild(7), "a < b");
+        assert_eq!(
+            wrapped,
+            "<metadata>\nGuild: 7 (7)\nChannel: None > 10 (10)\nUser: user-2 (2)\n</metadata>\n\n\
+             <message>a &lt; b</message>"
+        );
+
+        let wrapped = wrap_cli_message(2, 2, MemoryScope::DirectMessage, "hi");
+        assert!(wrapped.starts_with("<metadata>\nGuild: DM (0)\n"));
+    }
+}
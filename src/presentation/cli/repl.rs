@@ -0,0 +1,236 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::{
+    Services,
+    application::chat::chat_service::{ChatRequest, current_timestamp, process_message},
+    models::memory::{ChatMessage, ChatRole, MemoryScope, Role},
+    presentation::cli::wrap_cli_message,
+    shared::config::Config,
+};
+
+const HELP: &str = "\
+/context        モデルに送った内容を応答の前に表示する（もう一度で非表示）
+/history        短期記憶を表示する
+/clear          このチャンネルの短期記憶を消す
+/user <ID>      発言するユーザーを変える
+/channel <ID>   短期記憶のチャンネルを変える
+/guild <ID>     サーバーの記憶を使う
+/dm             DMの記憶を使う
+/reload         INSTRUCTION.md と設定を読み直す（短期記憶は残す）
+/quit           終了する";
+
+/// Discordの代わりに使う、仮のユーザーとチャンネル
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    #[arg(long, default_value_t = 1)]
+    user: u64,
+    /// 省略するとユーザーIDを使う（DMと同じ扱い）
+    #[arg(long)]
+    channel: Option<u64>,
+    /// サーバーの記憶を使う場合に指定する。省略するとDMの記憶を使う
+    #[arg(long)]
+    guild: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Input {
+    Message(String),
+    ToggleContext,
+    History,
+    Clear,
+    User(u64),
+    Channel(u64),
+    Guild(Option<u64>),
+    Reload,
+    Help,
+    Quit,
+    Invalid(String),
+}
+
+fn parse_input(line: &str) -> Option<Input> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let Some(command) = line.strip_prefix('/') else {
+        return Some(Input::Message(line.to_string()));
+    };
+
+    let (name, arg) = command
+        .split_once(char::is_whitespace)
+        .map(|(name, arg)| (name, arg.trim()))
+        .unwrap_or((command, ""));
+    let id = || arg.parse::<u64>().ok();
+
+    Some(match (name, id()) {
+        ("context", _) => Input::ToggleContext,
+        ("history", _) => Input::History,
+        ("clear", _) => Input::Clear,
+        ("user", Some(id)) => Input::User(id),
+        ("channel", Some(id)) => Input::Channel(id),
+        ("guild", Some(id)) => Input::Guild(Some(id)),
+        ("dm", _) => Input::Guild(None),
+        ("reload", _) => Input::Reload,
+        ("help", _) => Input::Help,
+        ("quit" | "exit", _) => Input::Quit,
+        ("user" | "channel" | "guild", None) => {
+            Input::Invalid(format!("/{name} には数値のIDを指定してください"))
+        }
+        _ => Input::Invalid(format!("不明なコマンドです: /{name}（/help で一覧）")),
+    })
+}
+
+/// 標準入力の発言を `process_message` で処理する。短期記憶はこのプロセスの中だけで保持する
+pub async fn run(args: ReplArgs, config: &Config) -> Result<()> {
+    let mut services = Services::for_cli(config).await?;
+    let mut user_id = args.user;
+    let mut channel_id = args.channel.unwrap_or(args.user);
+    let mut scope = MemoryScope::for_guild(args.guild);
+    let mut show_context = false;
+    // 同じ秒に送った発言が同じターンとして置き換えられないよう、連番にする
+    let mut next_turn_id = current_timestamp() as u64 * 1000;
+
+    println!("NekoAI REPL（/help でコマンド一覧、/quit で終了）");
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        eprint!("{}> ", describe_target(user_id, channel_id, scope));
+        let Some(line) = lines.next_line().await? else {
+            break;
+        };
+        let Some(input) = parse_input(&line) else {
+            continue;
+        };
+
+        match input {
+            Input::Message(user_message) => {
+                next_turn_id += 1;
+                let request = ChatRequest {
+                    channel_id,
+                    user_id,
+                    user_message: wrap_cli_message(user_id, channel_id, scope, &user_message),
+                    scope,
+                    turn_id: next_turn_id,
+                };
+
+                match process_message(
+                    services.ai_client.as_ref(),
+                    services.short_term_store.as_ref(),
+                    services.long_term_store.as_ref(),
+                    services.promotion_queue.as_ref(),
+                    request,
+                )
+                .await
+                {
+                    Ok(response) => {
+                        if show_context {
+                            print_context(&response.context);
+                        }
+                        println!("{}\n", response.content);
+                    }
+                    Err(err) => eprintln!("エラー: {err}\n"),
+                }
+            }
+            Input::ToggleContext => {
+                show_context = !show_context;
+                println!(
+                    "モデルに送る内容を{}",
+                    if show_context {
+                        "表示します"
+                    } else {
+                        "表示しません"
+                    }
+                );
+            }
+            Input::History => {
+                let messages = services.short_term_store.get_context(channel_id).await;
+                if messages.is_empty() {
+                    println!("（短期記憶は空です）");
+                }
+                for message in messages {
+                    let role = match message.role {
+                        Role::User => format!("user {}", message.user_id),
+                        Role::Assistant => "assistant".to_string(),
+                    };
+                    println!("[{role}] {}", message.content);
+                }
+            }
+            Input::Clear => {
+                services.short_term_store.clear(channel_id).await;
+                println!("短期記憶を消しました");
+            }
+            Input::User(id) => user_id = id,
+            Input::Channel(id) => channel_id = id,
+            Input::Guild(guild_id) => scope = MemoryScope::for_guild(guild_id),
+            Input::Reload => match reload(&services).await {
+                Ok(reloaded) => {
+                    services = reloaded;
+                    println!("INSTRUCTION.md と設定を読み直しました");
+                }
+                Err(err) => eprintln!("読み直せませんでした。今の設定のまま続けます: {err:#}"),
+            },
+            Input::Help => println!("{HELP}"),
+            Input::Quit => break,
+            Input::Invalid(message) => eprintln!("{message}"),
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_target(user_id: u64, channel_id: u64, scope: MemoryScope) -> String {
+    match scope {
+        MemoryScope::Guild(guild_id) => format!("user:{user_id} guild:{guild_id} ch:{channel_id}"),
+        MemoryScope::DirectMessage => format!("user:{user_id} dm ch:{channel_id}"),
+    }
+}
+
+/// 設定ファイルと INSTRUCTION.md を読み直してサービスを作り直す。短期記憶と昇格の再試行キューは引き継ぐ
+async fn reload(services: &Services) -> Result<Services> {
+    let config = Config::load().context("Failed to reload config")?;
+    let reloaded = Services::for_cli(&config)
+        .await
+        .context("Failed to reload services")?;
+    Ok(Services {
+        short_term_store: services.short_term_store.clone(),
+        promotion_queue: services.promotion_queue.clone(),
+        ..reloaded
+    })
+}
+
+/// 履歴の後に、ボットと同じ形に包んだ今回の発言が続く
+fn print_context(context: &[ChatMessage]) {
+    println!(
+        "----- context ({}件 + 発言) -----",
+        context.len().saturating_sub(1)
+    );
+    for message in context {
+        let role = match message.role {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        };
+        println!("[{role}] {}", message.content);
+    }
+    println!("---------------------------------");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_messages_and_commands() {
+        assert_eq!(parse_input("   "), None);
+        assert_eq!(
+            parse_input("こんにちは"),
+            Some(Input::Message("こんにちは".to_string()))
+        );
+        assert_eq!(parse_input("/context"), Some(Input::ToggleContext));
+        assert_eq!(parse_input("/user 42"), Some(Input::User(42)));
+        assert_eq!(parse_input("/guild  7 "), Some(Input::Guild(Some(7))));
+        assert_eq!(parse_input("/dm"), Some(Input::Guild(None)));
+        assert!(matches!(parse_input("/user abc"), Some(Input::Invalid(_))));
+        assert!(matches!(parse_input("/unknown"), Some(Input::Invalid(_))));
+    }
+}
@@ -0,0 +1,27 @@
+use crate::{
+    models::error::AppError,
+    presentation::{access::is_admin, command::command_registry::Context},
+};
+
+/// 管理コマンド用のチェック。サブコマンドには親コマンドのチェックも適用される
+pub async fn require_admin(ctx: Context<'_>) -> anyhow::Result<bool> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(false);
+    };
+
+    if is_admin(
+        ctx.serenity_context(),
+        ctx.data(),
+        guild_id,
+        ctx.author().id,
+    )
+    .await
+    {
+        Ok(true)
+    } else {
+        Err(AppError::PermissionDenied {
+            reason: format!("`{}` is admin only", ctx.command().qualified_name),
+        }
+        .into())
+    }
+}
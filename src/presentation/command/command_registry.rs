@@ -1,26 +1,95 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use poise::CreateReply;
+use serenity::all::GuildId;
 
 use crate::{
-    application::traits::{
-        ai_client::AIClient, long_term_store::LongTermStore, short_term_store::ShortTermStore,
+    application::{
+        health::health_service::HealthChecker,
+        traits::{
+            ai_client::AIClient, feedback_store::FeedbackStore, long_term_store::LongTermStore,
+            promotion_queue::PromotionQueue, settings_store::SettingsStore,
+            short_term_store::ShortTermStore,
+        },
+    },
+    models::error::AppError,
+    presentation::{
+        access::{check_access, is_admin, is_silenced},
+        auto_response::AutoResponseCooldowns,
+        command::handlers::*,
+        direct_message::DmMembership,
+        i18n::{command_locale, localize_commands, messages},
+        reply_tracker::ReplyTracker,
+    },
+    shared::{
+        config::{Attachments, CommandRegistration, DirectMessages, Guilds, Mentions},
+        metrics::metrics,
+        shutdown::InFlight,
     },
-    presentation::command::handlers::*,
 };
 
 pub struct Data {
     pub ai_client: Arc<dyn AIClient>,
     pub short_term_store: Arc<dyn ShortTermStore>,
     pub long_term_store: Arc<dyn LongTermStore>,
+    pub promotion_queue: Arc<dyn PromotionQueue>,
+    pub mentions: Mentions,
+    pub attachments: Attachments,
+    /// 設定で指定されたサーバー。コマンドの登録先とDMを許可するメンバーの判定に使う
+    pub guild_ids: Vec<u64>,
+    pub guilds: Guilds,
+    pub direct_messages: DirectMessages,
+    pub dm_membership: DmMembership,
+    pub settings_store: Arc<dyn SettingsStore>,
+    pub auto_response_cooldowns: AutoResponseCooldowns,
+    pub reply_tracker: ReplyTracker,
+    pub feedback_store: Arc<dyn FeedbackStore>,
+    /// 評価と一緒に記録する、生成に使ったモデル
+    pub model_name: String,
+    /// 評価と一緒に記録する、システムプロンプトの版
+    pub instruction_version: String,
+    pub started_at: Instant,
+    pub health_checker: HealthChecker,
+    /// 終了処理で、処理中のメッセージやコマンドが終わるのを待つのに使う
+    pub in_flight: Arc<InFlight>,
 }
 
-pub type Context<'a> = poise::Context<'a, Data, anyhow::Error>;
+pub const COMMAND_PREFIX: &str = "w!";
 
-async fn on_error(error: poise::FrameworkError<'_, Data, anyhow::Error>) {
+pub type Context<'a> = poise::Context<'a, Arc<Data>, anyhow::Error>;
+
+async fn on_error(error: poise::FrameworkError<'_, Arc<Data>, anyhow::Error>) {
     match error {
         poise::FrameworkError::Setup { error, .. } => panic!("Failed to start bot: {:?}", error),
         poise::FrameworkError::Command { error, ctx, .. } => {
             tracing::error!("Error in command `{}`: {:?}", ctx.command().name, error);
         }
+        poise::FrameworkError::CommandCheckFailed {
+            error: Some(error),
+            ctx,
+            ..
+        } => {
+            let Some(app_error) = error.downcast_ref::<AppError>() else {
+                tracing::error!("Error in check of `{}`: {:?}", ctx.command().name, error);
+                return;
+            };
+            metrics().record_error(app_error.kind());
+            tracing::info!(
+                user_id = %ctx.author().id,
+                "Denied command `{}`: {}",
+                ctx.command().qualified_name,
+                app_error
+            );
+            let reply = CreateReply::default()
+                .content(messages(command_locale(ctx).await).error(app_error))
+                .ephemeral(true);
+            if let Err(err) = ctx.send(reply).await {
+                tracing::warn!("Failed to send permission error: {err}");
+            }
+        }
         error => {
             if let Err(e) = poise::builtins::on_error(error).await {
                 tracing::error!("Error while handling error: {}", e);
@@ -29,25 +98,97 @@ async fn on_error(error: poise::FrameworkError<'_, Data, anyhow::Error>) {
     }
 }
 
-pub async fn command_framework(
-    guild_id: u64,
-    ai_client: Arc<dyn AIClient>,
-    short_term_store: Arc<dyn ShortTermStore>,
-    long_term_store: Arc<dyn LongTermStore>,
-) -> poise::framework::Framework<Data, anyhow::Error> {
-    let commands = vec![chat::chat(), health::health()];
+async fn command_check(ctx: Context<'_>) -> anyhow::Result<bool> {
+    let (serenity_ctx, data) = (ctx.serenity_context(), ctx.data());
+    let (guild_id, channel_id, user_id) = (ctx.guild_id(), ctx.channel_id(), ctx.author().id);
+
+    // 管理者はミュートしたチャンネルでも解除などの操作ができるようにする
+    if is_silenced(serenity_ctx, data, guild_id, channel_id, user_id).await {
+        let admin = match guild_id {
+            Some(guild_id) => is_admin(serenity_ctx, data, guild_id, user_id).await,
+            None => false,
+        };
+        if !admin {
+            return Err(AppError::PermissionDenied {
+                reason: "blocked user or muted channel".to_string(),
+            }
+            .into());
+        }
+    }
+
+    check_access(serenity_ctx, data, guild_id, channel_id, user_id, None).await?;
+    Ok(true)
+}
 
+pub fn commands() -> Vec<poise::Command<Arc<Data>, anyhow::Error>> {
+    let mut commands = vec![
+        chat::chat(),
+        health::health(),
+        language::language(),
+        help::help(),
+        autoreply::autoreply(),
+        feedback::feedback(),
+        access::access(),
+        admin::admin(),
+    ];
+    localize_commands(&mut commands);
+    commands
+}
+
+/// 設定に従ってスラッシュコマンドを登録する
+pub async fn register_commands(ctx: &serenity::all::Context, data: &Data) -> anyhow::Result<()> {
+    let commands = poise::builtins::create_application_commands(&commands());
+
+    match data.guilds.command_registration {
+        CommandRegistration::Guilds => {
+            for guild_id in &data.guild_ids {
+                // まだ参加していないサーバーは、参加した時に登録する
+                if let Err(err) = GuildId::new(*guild_id)
+                    .set_commands(ctx, commands.clone())
+                    .await
+                {
+                    tracing::warn!(guild_id, "Failed to register commands in guild: {err}");
+                }
+            }
+        }
+        CommandRegistration::Global => {
+            serenity::all::Command::set_global_commands(ctx, commands).await?;
+            // 以前サーバー単位で登録したコマンドが重複して表示されないよう消しておく
+            for guild_id in &data.guild_ids {
+                if let Err(err) = GuildId::new(*guild_id).set_commands(ctx, Vec::new()).await {
+                    tracing::warn!(guild_id, "Failed to clear guild commands: {err}");
+                }
+            }
+        }
+    }
+
+    tracing::info!(
+        registration = ?data.guilds.command_registration,
+        "Registered application commands"
+    );
+    Ok(())
+}
+
+pub async fn command_framework(
+    data: Arc<Data>,
+) -> poise::framework::Framework<Arc<Data>, anyhow::Error> {
     poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands,
+            commands: commands(),
             prefix_options: poise::PrefixFrameworkOptions {
-                prefix: Some("w!".into()),
+                prefix: Some(COMMAND_PREFIX.into()),
+                edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
+                    Duration::from_secs(60 * 60),
+                ))),
                 ..Default::default()
             },
             on_error: |error| Box::pin(on_error(error)),
+            // メンションと同じアクセス制御を全コマンドに適用する
+            command_check: Some(|ctx| Box::pin(command_check(ctx))),
             pre_command: |ctx| {
                 Box::pin(async move {
                     tracing::info!("Execute command {:#?}...", ctx.command().qualified_name);
+                    metrics().record_command(&ctx.command().qualified_name);
                 })
             },
             post_command: |ctx| {
@@ -57,19 +198,10 @@ pub async fn command_framework(
             },
             ..Default::default()
         })
-        .setup(move |ctx, _ready, framework| {
+        .setup(move |ctx, _ready, _framework| {
             Box::pin(async move {
-                poise::builtins::register_in_guild(
-                    ctx,
-                    &framework.options().commands,
-                    guild_id.into(),
-                )
-                .await?;
-                Ok(Data {
-                    ai_client,
-                    short_term_store,
-                    long_term_store,
-                })
+                register_commands(ctx, &data).await?;
+                Ok(data)
             })
         })
         .build()
@@ -0,0 +1,238 @@
+use poise::CreateReply;
+use serenity::all::{GuildChannel, Role, User};
+
+use crate::{
+    models::settings::{AccessList, AccessTarget},
+    presentation::{
+        command::{checks::require_admin, command_registry::Context},
+        i18n::{Messages, command_locale, messages},
+    },
+};
+
+/// ボットを使えるユーザー・ロール・チャンネルを管理する
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("show", "allow", "deny", "remove", "admin_role"),
+    subcommand_required,
+    check = "require_admin"
+)]
+pub async fn access(_ctx: Context<'_>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// 現在のアクセス制御の設定
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn show(ctx: Context<'_>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let messages = messages(command_locale(ctx).await);
+    let rules = ctx.data().settings_store.guild(guild_id.get()).await.access;
+    let admin_roles = if rules.admin_roles.is_empty() {
+        messages.none().to_string()
+    } else {
+        mentions(&rules.admin_roles, "@&")
+    };
+    let content = messages.access_rules(
+        &describe(&rules.allow, messages),
+        &describe(&rules.deny, messages),
+        &admin_roles,
+    );
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// 許可リストに追加する（一致する場合だけ利用可能になる）
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn allow(
+    ctx: Context<'_>,
+    #[description = "ユーザー"] user: Option<User>,
+    #[description = "ロール"] role: Option<Role>,
+    #[description = "チャンネルまたはカテゴリー"] channel: Option<GuildChannel>,
+) -> anyhow::Result<()> {
+    let targets = targets(user, role, channel);
+    update_rules(ctx, targets, ListChange::Allow).await
+}
+
+/// 拒否リストに追加する。許可リストより優先される
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn deny(
+    ctx: Context<'_>,
+    #[description = "ユーザー"] user: Option<User>,
+    #[description = "ロール"] role: Option<Role>,
+    #[description = "チャンネルまたはカテゴリー"] channel: Option<GuildChannel>,
+) -> anyhow::Result<()> {
+    let targets = targets(user, role, channel);
+    update_rules(ctx, targets, ListChange::Deny).await
+}
+
+/// 許可リストと拒否リストの両方から取り除く
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "ユーザー"] user: Option<User>,
+    #[description = "ロール"] role: Option<Role>,
+    #[description = "チャンネルまたはカテゴリー"] channel: Option<GuildChannel>,
+) -> anyhow::Result<()> {
+    let targets = targets(user, role, channel);
+    update_rules(ctx, targets, ListChange::Remove).await
+}
+
+/// 管理コマンドを使えるロールを設定する
+#[poise::command(slash_command, prefix_command, guild_only, rename = "admin-role")]
+pub async fn admin_role(
+    ctx: Context<'_>,
+    #[description = "ロール"] role: Role,
+    #[description = "管理者にするか（falseで解除）"] enabled: bool,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let role_id = role.id.get();
+    ctx.data()
+        .settings_store
+        .update_guild(
+            guild_id.get(),
+            Box::new(move |settings| {
+                let roles = &mut settings.access.admin_roles;
+                roles.retain(|id| *id != role_id);
+                if enabled {
+                    roles.push(role_id);
+                }
+            }),
+        )
+        .await?;
+
+    tracing::info!(
+        guild_id = guild_id.get(),
+        role_id,
+        enabled,
+        "Bot admin role changed"
+    );
+
+    let content = messages(command_locale(ctx).await).admin_role_changed(role_id, enabled);
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ListChange {
+    Allow,
+    Deny,
+    Remove,
+}
+
+fn targets(
+    user: Option<User>,
+    role: Option<Role>,
+    channel: Option<GuildChannel>,
+) -> Vec<AccessTarget> {
+    user.map(|u| AccessTarget::User(u.id.get()))
+        .into_iter()
+        .chain(role.map(|r| AccessTarget::Role(r.id.get())))
+        .chain(channel.map(|c| AccessTarget::Channel(c.id.get())))
+        .collect()
+}
+
+async fn update_rules(
+    ctx: Context<'_>,
+    targets: Vec<AccessTarget>,
+    change: ListChange,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let messages = messages(command_locale(ctx).await);
+
+    if targets.is_empty() {
+        ctx.send(
+            CreateReply::default()
+                .content(messages.access_target_required())
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let applied = targets.clone();
+    ctx.data()
+        .settings_store
+        .update_guild(
+            guild_id.get(),
+            Box::new(move |settings| {
+                let rules = &mut settings.access;
+                for target in applied {
+                    // 同じ対象が両方のリストに載らないようにする
+                    rules.allow.remove(target);
+                    rules.deny.remove(target);
+                    match change {
+                        ListChange::Allow => rules.allow.insert(target),
+                        ListChange::Deny => rules.deny.insert(target),
+                        ListChange::Remove => {}
+                    }
+                }
+            }),
+        )
+        .await?;
+
+    tracing::info!(
+        guild_id = guild_id.get(),
+        ?targets,
+        ?change,
+        "Access rules changed"
+    );
+
+    let names = targets
+        .iter()
+        .map(|t| mention(*t))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let content = match change {
+        ListChange::Allow => messages.access_allowed(&names),
+        ListChange::Deny => messages.access_denied(&names),
+        ListChange::Remove => messages.access_removed(&names),
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+fn describe(list: &AccessList, messages: &dyn Messages) -> String {
+    if list.is_empty() {
+        return format!("- {}", messages.none());
+    }
+
+    let mut lines = Vec::new();
+    if !list.users.is_empty() {
+        lines.push(messages.access_users(&mentions(&list.users, "@")));
+    }
+    if !list.roles.is_empty() {
+        lines.push(messages.access_roles(&mentions(&list.roles, "@&")));
+    }
+    if !list.channels.is_empty() {
+        lines.push(messages.access_channels(&mentions(&list.channels, "#")));
+    }
+    lines.join("\n")
+}
+
+fn mention(target: AccessTarget) -> String {
+    match target {
+        AccessTarget::User(id) => format!("<@{id}>"),
+        AccessTarget::Role(id) => format!("<@&{id}>"),
+        AccessTarget::Channel(id) => format!("<#{id}>"),
+    }
+}
+
+fn mentions(ids: &[u64], prefix: &str) -> String {
+    ids.iter()
+        .map(|id| format!("<{prefix}{id}>"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
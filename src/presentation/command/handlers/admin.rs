@@ -0,0 +1,206 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use poise::CreateReply;
+use serenity::all::{GuildChannel, User};
+
+use crate::presentation::{
+    command::{checks::require_admin, command_registry::Context},
+    i18n::{command_locale, messages},
+};
+
+/// 特定のユーザーやチャンネルでボットを黙らせる
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "block",
+        "unblock",
+        "mute_channel",
+        "unmute_channel",
+        "blocked",
+        "muted"
+    ),
+    subcommand_required,
+    check = "require_admin"
+)]
+pub async fn admin(_ctx: Context<'_>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// ユーザーの発言やコマンドに一切応答しないようにする
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn block(
+    ctx: Context<'_>,
+    #[description = "対象のユーザー"] user: User,
+) -> anyhow::Result<()> {
+    set_blocked(ctx, user, true).await
+}
+
+/// ユーザーのブロックを解除する
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn unblock(
+    ctx: Context<'_>,
+    #[description = "対象のユーザー"] user: User,
+) -> anyhow::Result<()> {
+    set_blocked(ctx, user, false).await
+}
+
+/// チャンネルでは一切応答しないようにする
+#[poise::command(slash_command, prefix_command, guild_only, rename = "mute-channel")]
+pub async fn mute_channel(
+    ctx: Context<'_>,
+    #[description = "対象のチャンネル（省略時はこのチャンネル）"] channel: Option<GuildChannel>,
+) -> anyhow::Result<()> {
+    set_muted(ctx, channel, true).await
+}
+
+/// チャンネルのミュートを解除する
+#[poise::command(slash_command, prefix_command, guild_only, rename = "unmute-channel")]
+pub async fn unmute_channel(
+    ctx: Context<'_>,
+    #[description = "対象のチャンネル（省略時はこのチャンネル）"] channel: Option<GuildChannel>,
+) -> anyhow::Result<()> {
+    set_muted(ctx, channel, false).await
+}
+
+/// ブロックしているユーザーの一覧
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn blocked(ctx: Context<'_>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let moderation = ctx
+        .data()
+        .settings_store
+        .guild(guild_id.get())
+        .await
+        .moderation;
+    let messages = messages(command_locale(ctx).await);
+    let content = if moderation.blocked_users.is_empty() {
+        messages.blocked_none().to_string()
+    } else {
+        let lines: Vec<String> = moderation
+            .blocked_users
+            .iter()
+            .map(|id| format!("- <@{id}>"))
+            .collect();
+        messages.blocked_users(&lines.join("\n"))
+    };
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// ミュートしているチャンネルの一覧
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn muted(ctx: Context<'_>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let moderation = ctx
+        .data()
+        .settings_store
+        .guild(guild_id.get())
+        .await
+        .moderation;
+    let messages = messages(command_locale(ctx).await);
+    let content = if moderation.muted_channels.is_empty() {
+        messages.muted_none().to_string()
+    } else {
+        let lines: Vec<String> = moderation
+            .muted_channels
+            .iter()
+            .map(|id| format!("- <#{id}>"))
+            .collect();
+        messages.muted_channels(&lines.join("\n"))
+    };
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+async fn set_blocked(ctx: Context<'_>, user: User, blocked: bool) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let user_id = user.id.get();
+    // 同時に実行されても結果が食い違わないよう、変わったかどうかは更新の中で判定する
+    let changed = Arc::new(AtomicBool::new(false));
+    let changed_in_update = changed.clone();
+    ctx.data()
+        .settings_store
+        .update_guild(
+            guild_id.get(),
+            Box::new(move |settings| {
+                let changed = settings.moderation.set_blocked(user_id, blocked);
+                changed_in_update.store(changed, Ordering::Relaxed);
+            }),
+        )
+        .await?;
+    let changed = changed.load(Ordering::Relaxed);
+
+    if changed {
+        tracing::info!(
+            guild_id = guild_id.get(),
+            user_id,
+            blocked,
+            "User block changed"
+        );
+    }
+
+    let content = messages(command_locale(ctx).await).block_changed(user_id, blocked, changed);
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+async fn set_muted(
+    ctx: Context<'_>,
+    channel: Option<GuildChannel>,
+    muted: bool,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let channel_id = channel
+        .map(|c| c.id)
+        .unwrap_or_else(|| ctx.channel_id())
+        .get();
+    let changed = Arc::new(AtomicBool::new(false));
+    let changed_in_update = changed.clone();
+    ctx.data()
+        .settings_store
+        .update_guild(
+            guild_id.get(),
+            Box::new(move |settings| {
+                let changed = settings.moderation.set_muted(channel_id, muted);
+                changed_in_update.store(changed, Ordering::Relaxed);
+            }),
+        )
+        .await?;
+    let changed = changed.load(Ordering::Relaxed);
+
+    if changed {
+        tracing::info!(
+            guild_id = guild_id.get(),
+            channel_id,
+            muted,
+            "Channel mute changed"
+        );
+    }
+
+    let content = messages(command_locale(ctx).await).mute_changed(channel_id, muted, changed);
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
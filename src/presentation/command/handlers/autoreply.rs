@@ -0,0 +1,102 @@
+use poise::CreateReply;
+use serenity::all::GuildChannel;
+
+use crate::{
+    models::settings::ChannelMode,
+    presentation::{
+        command::{checks::require_admin, command_registry::Context},
+        i18n::{command_locale, messages},
+    },
+};
+
+/// メンション無しで応答するチャンネルを管理する
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("on", "off", "list"),
+    subcommand_required,
+    check = "require_admin"
+)]
+pub async fn autoreply(_ctx: Context<'_>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// チャンネルでメンション無しの自動応答を有効にする
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn on(
+    ctx: Context<'_>,
+    #[description = "対象のチャンネル（省略時はこのチャンネル）"] channel: Option<GuildChannel>,
+) -> anyhow::Result<()> {
+    set_mode(ctx, channel, ChannelMode::AutoRespond).await
+}
+
+/// チャンネルをメンションされた時だけ応答する状態に戻す
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn off(
+    ctx: Context<'_>,
+    #[description = "対象のチャンネル（省略時はこのチャンネル）"] channel: Option<GuildChannel>,
+) -> anyhow::Result<()> {
+    set_mode(ctx, channel, ChannelMode::MentionOnly).await
+}
+
+/// 自動応答が有効なチャンネルの一覧
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn list(ctx: Context<'_>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let settings = ctx.data().settings_store.guild(guild_id.get()).await;
+    let mut channels: Vec<u64> = settings
+        .channel_modes
+        .iter()
+        .filter(|(_, mode)| **mode == ChannelMode::AutoRespond)
+        .map(|(id, _)| *id)
+        .collect();
+    channels.sort_unstable();
+
+    let messages = messages(command_locale(ctx).await);
+    let content = if channels.is_empty() {
+        messages.autoreply_none().to_string()
+    } else {
+        let lines: Vec<String> = channels.iter().map(|id| format!("- <#{id}>")).collect();
+        messages.autoreply_channels(&lines.join("\n"))
+    };
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+async fn set_mode(
+    ctx: Context<'_>,
+    channel: Option<GuildChannel>,
+    mode: ChannelMode,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let channel_id = channel.map(|c| c.id).unwrap_or_else(|| ctx.channel_id());
+
+    ctx.data()
+        .settings_store
+        .update_guild(
+            guild_id.get(),
+            Box::new(move |settings| settings.set_channel_mode(channel_id.get(), mode)),
+        )
+        .await?;
+
+    tracing::info!(
+        guild_id = guild_id.get(),
+        channel_id = channel_id.get(),
+        ?mode,
+        "Channel mode changed"
+    );
+
+    let content = messages(command_locale(ctx).await).channel_mode_changed(channel_id.get(), mode);
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
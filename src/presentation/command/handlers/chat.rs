@@ -1,26 +1,65 @@
+use poise::CreateReply;
+use serenity::all::CreateMessage;
+
 use crate::{
-    application::chat::chat_service::process_message,
-    presentation::command::command_registry::Context, shared::discord_utils::split_message,
+    application::chat::{
+        chat_service::{ChatRequest, ChatResponse, process_message},
+        prompt_envelope::wrap_user_message,
+    },
+    models::memory::{MemoryScope, TokenUsage},
+    presentation::{
+        command::command_registry::Context,
+        direct_message::direct_message_allowed,
+        i18n::{command_locale, messages},
+        metadata::collect_metadata,
+        outbound::prepare_reply,
+        reply_components::reply_buttons,
+        reply_sender::delete_messages,
+        reply_tracker::{Generation, TrackedReply},
+    },
 };
 
-#[poise::command(prefix_command, slash_command)]
+/// `w!chat` の呼び出しメッセージを編集すると、応答を作り直して書き換える
+#[poise::command(prefix_command, slash_command, track_edits)]
 pub async fn chat(
     ctx: Context<'_>,
     #[description = "Prompt"] prompt: String,
 ) -> anyhow::Result<()> {
+    let data = ctx.data();
+    let is_direct_message = ctx.guild_id().is_none();
+    let locale = command_locale(ctx).await;
+
+    if is_direct_message
+        && !direct_message_allowed(ctx.serenity_context(), data, ctx.author().id).await
+    {
+        ctx.say(messages(locale).direct_messages_unavailable())
+            .await?;
+        return Ok(());
+    }
+
     let _typing = ctx.channel_id().start_typing(&ctx.serenity_context().http);
 
-    let data = ctx.data();
     let channel_id = ctx.channel_id().get();
     let user_id = ctx.author().id.get();
 
+    let metadata = collect_metadata(ctx.cache(), ctx.guild_id(), ctx.channel_id(), ctx.author());
+    let content = wrap_user_message(&metadata, &[], &prompt);
+    let policy = data.mentions.policy_for(ctx.guild_id().map(|id| id.get()));
+
+    let request = ChatRequest {
+        channel_id,
+        user_id,
+        user_message: content.clone(),
+        scope: MemoryScope::for_guild(ctx.guild_id().map(|id| id.get())),
+        turn_id: ctx.id(),
+    };
+
     let reply = match process_message(
         data.ai_client.as_ref(),
         data.short_term_store.as_ref(),
         data.long_term_store.as_ref(),
-        channel_id,
-        user_id,
-        prompt,
+        data.promotion_queue.as_ref(),
+        request,
     )
     .await
     {
@@ -32,20 +71,81 @@ pub async fn chat(
                 error = %err,
                 "Failed to process message"
             );
-            err.user_facing_message().to_string()
+            ChatResponse {
+                content: messages(locale).error(&err).to_string(),
+                memories: Vec::new(),
+                usage: TokenUsage::default(),
+                context: Vec::new(),
+            }
         }
     };
 
-    let chunks = split_message(&reply);
-    for (i, chunk) in chunks.iter().enumerate() {
+    let outbound = prepare_reply(&reply.content, policy, user_id, &prompt);
+
+    // 編集による再実行では、1通目はpoiseが書き換えるので、前回の2通目以降を消してから送り直す
+    if let Some(previous) = data.reply_tracker.get(ctx.id()) {
+        delete_messages(
+            &ctx.serenity_context().http,
+            ctx.channel_id(),
+            previous.reply_message_ids.into_iter().skip(1),
+        )
+        .await;
+    }
+
+    let buttons = reply_buttons(ctx.id(), user_id, locale);
+    let messages = outbound.messages(&data.attachments, locale);
+    let last = messages.len().saturating_sub(1);
+
+    let mut reply_message_ids = Vec::new();
+    for (i, message) in messages.into_iter().enumerate() {
+        let components = if i == last {
+            buttons.clone()
+        } else {
+            Vec::new()
+        };
+
         if i == 0 {
-            ctx.say(*chunk).await?;
+            let reply = message.files.into_iter().fold(
+                CreateReply::default()
+                    .content(message.content)
+                    .allowed_mentions(outbound.allowed_mentions.clone())
+                    .components(components),
+                |reply, file| reply.attachment(file),
+            );
+            let handle = ctx.send(reply).await?;
+            if let Ok(sent) = handle.message().await {
+                reply_message_ids.push(sent.id.get());
+            }
         } else {
-            ctx.channel_id()
-                .say(&ctx.serenity_context().http, *chunk)
+            let sent = ctx
+                .channel_id()
+                .send_message(
+                    &ctx.serenity_context().http,
+                    CreateMessage::new()
+                        .content(message.content)
+                        .add_files(message.files)
+                        .allowed_mentions(outbound.allowed_mentions.clone())
+                        .components(components),
+                )
                 .await?;
+            reply_message_ids.push(sent.id.get());
         }
     }
 
+    data.reply_tracker.track(
+        ctx.id(),
+        TrackedReply {
+            channel_id,
+            user_id,
+            source_content: prompt,
+            reply_message_ids,
+            generation: Generation {
+                prompt: content,
+                memories: reply.memories,
+                response: reply.content,
+            },
+        },
+    );
+
     Ok(())
 }
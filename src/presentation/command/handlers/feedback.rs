@@ -0,0 +1,52 @@
+use poise::CreateReply;
+
+use crate::{
+    application::feedback::feedback_service::summarize,
+    presentation::{
+        command::{checks::require_admin, command_registry::Context},
+        i18n::{command_locale, messages},
+    },
+};
+
+/// 応答への評価を確認する
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("stats"),
+    subcommand_required,
+    check = "require_admin"
+)]
+pub async fn feedback(_ctx: Context<'_>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// モデルとシステムプロンプトの版ごとの👍/👎の集計
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn stats(ctx: Context<'_>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let messages = messages(command_locale(ctx).await);
+    let feedback = ctx.data().feedback_store.list(Some(guild_id.get())).await;
+    let summaries = summarize(&feedback);
+
+    let content = if summaries.is_empty() {
+        messages.feedback_empty().to_string()
+    } else {
+        let lines: Vec<String> = summaries
+            .iter()
+            .map(|s| messages.feedback_line(s))
+            .collect();
+        messages.feedback_summary(
+            &ctx.data().model_name,
+            &ctx.data().instruction_version,
+            &lines.join("\n"),
+        )
+    };
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -1,7 +1,56 @@
-use crate::presentation::command::command_registry::Context;
+use crate::presentation::{
+    command::command_registry::Context,
+    health::{format_uptime, gateway_status, health_report},
+    i18n::{command_locale, messages},
+};
 
+/// ボットと依存先の状態を確認する
 #[poise::command(slash_command, prefix_command)]
 pub async fn health(ctx: Context<'_>) -> anyhow::Result<()> {
-    ctx.say("Ok!").await?;
+    ctx.defer().await?;
+    let locale = command_locale(ctx).await;
+    let messages = messages(locale);
+
+    let shard_manager = ctx.framework().shard_manager();
+    let (gateway, report) = tokio::join!(gateway_status(&shard_manager), health_report(ctx.data()));
+
+    let mut lines = vec![
+        messages
+            .health_status(report.is_healthy() && gateway.is_connected())
+            .to_string(),
+    ];
+
+    lines.push(format!(
+        "- {} {}",
+        mark(gateway.is_connected()),
+        messages.health_gateway(gateway.connected, gateway.shards, gateway.latency_ms),
+    ));
+    for check in &report.checks {
+        let detail = if check.detail.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", check.detail)
+        };
+        lines.push(format!(
+            "- {} {}: {}ms{detail}",
+            mark(check.healthy),
+            check.name,
+            check.latency.as_millis()
+        ));
+    }
+    lines.push(format!(
+        "- {}",
+        messages.health_short_term(report.short_term.channels, report.short_term.messages)
+    ));
+    lines.push(format!(
+        "- {}",
+        messages.health_uptime(&format_uptime(report.uptime, locale))
+    ));
+
+    ctx.say(lines.join("\n")).await?;
     Ok(())
 }
+
+fn mark(healthy: bool) -> &'static str {
+    if healthy { "✅" } else { "❌" }
+}
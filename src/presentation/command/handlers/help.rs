@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use poise::CreateReply;
+
+use crate::{
+    models::locale::Locale,
+    presentation::{
+        command::command_registry::{Context, Data},
+        i18n::{command_description, command_locale, messages},
+    },
+};
+
+/// 使えるコマンドの一覧
+#[poise::command(slash_command, prefix_command)]
+pub async fn help(ctx: Context<'_>) -> anyhow::Result<()> {
+    let locale = command_locale(ctx).await;
+    let messages = messages(locale);
+
+    let mut lines = vec![messages.help_header().to_string()];
+    for command in &ctx.framework().options().commands {
+        push_command(&mut lines, "", command, locale);
+    }
+    lines.push(String::new());
+    lines.push(messages.help_footer().to_string());
+
+    ctx.send(
+        CreateReply::default()
+            .content(lines.join("\n"))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// サブコマンドを持つコマンドは、実行できるサブコマンドだけを載せる
+fn push_command(
+    lines: &mut Vec<String>,
+    parent: &str,
+    command: &poise::Command<Arc<Data>, anyhow::Error>,
+    locale: Locale,
+) {
+    let path = format!("{parent}{}", command.name);
+    if command.subcommands.is_empty() {
+        lines.push(format!(
+            "- `/{path}`: {}",
+            command_description(command, locale).unwrap_or_default()
+        ));
+    }
+    for subcommand in &command.subcommands {
+        push_command(lines, &format!("{path} "), subcommand, locale);
+    }
+}
@@ -0,0 +1,70 @@
+use poise::CreateReply;
+
+use crate::{
+    models::locale::Locale,
+    presentation::{
+        command::command_registry::Context,
+        i18n::{command_locale, messages},
+    },
+};
+
+/// `/language` の選択肢
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum LanguageChoice {
+    #[name = "自動"]
+    #[name = "auto"]
+    #[name_localized("en-US", "Auto")]
+    #[name_localized("en-GB", "Auto")]
+    Auto,
+    #[name = "日本語"]
+    #[name = "ja"]
+    #[name_localized("en-US", "Japanese")]
+    #[name_localized("en-GB", "Japanese")]
+    Japanese,
+    #[name = "English"]
+    #[name = "en"]
+    English,
+}
+
+impl LanguageChoice {
+    fn locale(self) -> Option<Locale> {
+        match self {
+            Self::Auto => None,
+            Self::Japanese => Some(Locale::Ja),
+            Self::English => Some(Locale::En),
+        }
+    }
+}
+
+/// ボットのメッセージの表示言語を選ぶ
+#[poise::command(slash_command, prefix_command)]
+pub async fn language(
+    ctx: Context<'_>,
+    #[description = "言語（省略すると現在の設定を表示）"] language: Option<LanguageChoice>,
+) -> anyhow::Result<()> {
+    let user_id = ctx.author().id.get();
+
+    let content = match language {
+        Some(choice) => {
+            let preferred = choice.locale();
+            ctx.data()
+                .settings_store
+                .update_user(
+                    user_id,
+                    Box::new(move |settings| settings.locale = preferred),
+                )
+                .await?;
+            tracing::info!(user_id, ?preferred, "User language changed");
+            // 変更後の言語で返す
+            messages(command_locale(ctx).await).language_changed(preferred)
+        }
+        None => {
+            let preferred = ctx.data().settings_store.user(user_id).await.locale;
+            messages(command_locale(ctx).await).language_current(preferred)
+        }
+    };
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -1,2 +1,8 @@
+pub mod access;
+pub mod admin;
+pub mod autoreply;
 pub mod chat;
+pub mod feedback;
 pub mod health;
+pub mod help;
+pub mod language;
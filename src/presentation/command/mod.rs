@@ -1,2 +1,4 @@
+pub mod checks;
 pub mod command_registry;
 pub mod handlers;
+pub mod traced_framework;
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Client, Context, FullEvent, Interaction},
+    async_trait,
+    framework::Framework,
+};
+use tracing::Instrument;
+
+use crate::{presentation::command::command_registry::COMMAND_PREFIX, shared::shutdown::InFlight};
+
+/// コマンドの実行をチャンネル・ユーザー・サーバーのフィールド付きのスパンで囲む。
+/// 終了処理が始まった後のコマンドは受け付けない
+pub struct TracedFramework<F> {
+    inner: F,
+    in_flight: Arc<InFlight>,
+}
+
+impl<F> TracedFramework<F> {
+    pub fn new(inner: F, in_flight: Arc<InFlight>) -> Self {
+        Self { inner, in_flight }
+    }
+}
+
+#[async_trait]
+impl<F: Framework> Framework for TracedFramework<F> {
+    async fn init(&mut self, client: &Client) {
+        self.inner.init(client).await;
+    }
+
+    async fn dispatch(&self, ctx: Context, event: FullEvent) {
+        let Some(_in_flight) = self.in_flight.begin() else {
+            return;
+        };
+        let span = command_span(&event);
+        self.inner.dispatch(ctx, event).instrument(span).await;
+    }
+}
+
+fn command_span(event: &FullEvent) -> tracing::Span {
+    match event {
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Command(command),
+        } => tracing::info_span!(
+            "command",
+            command = command.data.name.as_str(),
+            channel_id = command.channel_id.get(),
+            user_id = command.user.id.get(),
+            guild_id = command.guild_id.map(|id| id.get()),
+        ),
+        FullEvent::Message { new_message } => {
+            let Some(invocation) = new_message.content.strip_prefix(COMMAND_PREFIX) else {
+                return tracing::Span::none();
+            };
+            tracing::info_span!(
+                "command",
+                command = invocation.split_whitespace().next().unwrap_or_default(),
+                channel_id = new_message.channel_id.get(),
+                user_id = new_message.author.id.get(),
+                guild_id = new_message.guild_id.map(|id| id.get()),
+            )
+        }
+        _ => tracing::Span::none(),
+    }
+}
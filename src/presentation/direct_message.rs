@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serenity::all::{Context, GuildId, UserId};
+
+use crate::{presentation::command::command_registry::Data, shared::config::DirectMessageMode};
+
+/// APIで確かめたメンバーかどうかを使い回す時間
+const MEMBERSHIP_TTL: Duration = Duration::from_secs(600);
+
+/// これを超えたら期限切れの記録を掃除する
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// DMを送ってきたユーザーがサーバーのメンバーかどうかの、APIで確かめた結果。
+/// GUILD_MEMBERS インテントが無くキャッシュには発言したメンバーしか載らないため、載っていなければAPIで確かめる。
+/// DMのたびにサーバーの数だけリクエストが飛ばないよう、結果を使い回し、確認は同時に1件ずつ行う
+pub struct DmMembership {
+    ttl: Duration,
+    results: Mutex<HashMap<u64, (bool, Instant)>>,
+    lookups: tokio::sync::Mutex<()>,
+}
+
+impl Default for DmMembership {
+    fn default() -> Self {
+        Self::new(MEMBERSHIP_TTL)
+    }
+}
+
+impl DmMembership {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            results: Mutex::new(HashMap::new()),
+            lookups: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn cached(&self, user_id: u64, now: Instant) -> Option<bool> {
+        let results = self
+            .results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        results
+            .get(&user_id)
+            .filter(|(_, at)| now.duration_since(*at) < self.ttl)
+            .map(|(is_member, _)| *is_member)
+    }
+
+    /// キャッシュに載っていればそれに従い、載っていなければ `lookup` でAPIに確かめる
+    async fn check(
+        &self,
+        user_id: u64,
+        in_cache: bool,
+        lookup: impl AsyncFnOnce() -> bool,
+    ) -> bool {
+        if in_cache {
+            return true;
+        }
+        if let Some(is_member) = self.cached(user_id, Instant::now()) {
+            return is_member;
+        }
+
+        let _lookup = self.lookups.lock().await;
+        // 待っている間に、同じユーザーの確認が終わっているかもしれない
+        if let Some(is_member) = self.cached(user_id, Instant::now()) {
+            return is_member;
+        }
+        let is_member = lookup().await;
+
+        let now = Instant::now();
+        let mut results = self
+            .results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if results.len() > PRUNE_THRESHOLD {
+            let ttl = self.ttl;
+            results.retain(|_, (_, at)| now.duration_since(*at) < ttl);
+        }
+        results.insert(user_id, (is_member, now));
+        is_member
+    }
+}
+
+pub async fn direct_message_allowed(ctx: &Context, data: &Data, user_id: UserId) -> bool {
+    let allowed = allowed_by_mode(data.direct_messages.mode, || {
+        is_member_of_dm_guilds(ctx, data, user_id)
+    })
+    .await;
+    if !allowed {
+        tracing::debug!(%user_id, mode = ?data.direct_messages.mode, "Rejected direct message");
+    }
+    allowed
+}
+
+/// DMの受付方針に従って判定する。サーバーのメンバーかどうかは GuildMembers の時だけ確かめる
+async fn allowed_by_mode<F>(mode: DirectMessageMode, is_member: impl FnOnce() -> F) -> bool
+where
+    F: Future<Output = bool>,
+{
+    match mode {
+        DirectMessageMode::Disabled => false,
+        DirectMessageMode::Everyone => true,
+        DirectMessageMode::GuildMembers => is_member().await,
+    }
+}
+
+/// DMを許可するかの判定に使うサーバー。指定されていなければ、ボットが参加している全サーバー
+pub fn dm_guilds(ctx: &Context, data: &Data) -> Vec<GuildId> {
+    if data.guild_ids.is_empty() {
+        ctx.cache.guilds()
+    } else {
+        data.guild_ids.iter().map(|id| GuildId::new(*id)).collect()
+    }
+}
+
+async fn is_member_of_dm_guilds(ctx: &Context, data: &Data, user_id: UserId) -> bool {
+    let guild_ids = dm_guilds(ctx, data);
+    let in_cache = guild_ids.iter().any(|guild_id| {
+        ctx.cache
+            .guild(*guild_id)
+            .is_some_and(|guild| guild.members.contains_key(&user_id))
+    });
+
+    data.dm_membership
+        .check(user_id.get(), in_cache, async || {
+            for guild_id in guild_ids {
+                if guild_id.member(ctx, user_id).await.is_ok() {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn unreachable_membership() -> bool {
+        panic!("membership should not be checked")
+    }
+
+    #[tokio::test]
+    async fn disabled_rejects_without_checking_membership() {
+        assert!(!allowed_by_mode(DirectMessageMode::Disabled, unreachable_membership).await);
+    }
+
+    #[tokio::test]
+    async fn everyone_accepts_without_checking_membership() {
+        assert!(allowed_by_mode(DirectMessageMode::Everyone, unreachable_membership).await);
+    }
+
+    #[tokio::test]
+    async fn members_missing_from_the_cache_are_looked_up_once() {
+        let membership = DmMembership::default();
+
+        assert!(membership.check(1, true, unreachable_membership).await);
+        // 発言していないメンバーはキャッシュに載っていないので、APIで確かめる
+        assert!(membership.check(2, false, async || true).await);
+        assert!(membership.check(2, false, unreachable_membership).await);
+        assert!(!membership.check(3, false, async || false).await);
+        assert!(!membership.check(3, false, unreachable_membership).await);
+    }
+
+    #[tokio::test]
+    async fn membership_is_looked_up_again_after_ttl() {
+        let membership = DmMembership::new(Duration::ZERO);
+
+        assert!(!membership.check(1, false, async || false).await);
+        assert!(membership.check(1, false, async || true).await);
+    }
+
+    #[tokio::test]
+    async fn guild_members_follows_membership() {
+        assert!(allowed_by_mode(DirectMessageMode::GuildMembers, || async { true }).await);
+        assert!(!allowed_by_mode(DirectMessageMode::GuildMembers, || async { false }).await);
+    }
+}
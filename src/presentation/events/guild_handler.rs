@@ -0,0 +1,60 @@
+use serenity::all::{Context, Guild, UnavailableGuild};
+
+use crate::{
+    presentation::command::command_registry::{Data, commands},
+    shared::config::CommandRegistration,
+};
+
+/// サーバーへの参加。対象のサーバーなら、起動時に登録できなかったコマンドを登録する
+pub async fn guild_create(ctx: Context, guild: Guild, is_new: Option<bool>, data: &Data) {
+    if is_new != Some(true) {
+        return;
+    }
+
+    let guild_id = guild.id.get();
+    tracing::info!(guild_id, name = %guild.name, "Joined guild");
+
+    if data.guilds.command_registration == CommandRegistration::Guilds
+        && data.guild_ids.contains(&guild_id)
+    {
+        let commands = poise::builtins::create_application_commands(&commands());
+        if let Err(err) = guild.id.set_commands(&ctx, commands).await {
+            tracing::warn!(
+                guild_id,
+                "Failed to register commands in joined guild: {err}"
+            );
+        }
+    }
+}
+
+/// サーバーからの退出。障害による一時的な利用不可の場合は何もしない
+pub async fn guild_delete(incomplete: UnavailableGuild, full: Option<Guild>, data: &Data) {
+    if incomplete.unavailable {
+        tracing::warn!(guild_id = %incomplete.id, "Guild became unavailable");
+        return;
+    }
+
+    let guild_id = incomplete.id.get();
+    tracing::info!(guild_id, "Removed from guild");
+
+    if let Err(err) = data.settings_store.remove_guild(guild_id).await {
+        tracing::warn!(guild_id, "Failed to remove guild settings: {err}");
+    }
+
+    // キャッシュに残っていたチャンネルの会話を忘れる
+    if let Some(guild) = full {
+        let channel_ids = guild
+            .channels
+            .keys()
+            .chain(guild.threads.iter().map(|thread| &thread.id));
+        for channel_id in channel_ids {
+            data.short_term_store.clear(channel_id.get()).await;
+        }
+    }
+
+    if data.guilds.purge_memory_on_leave
+        && let Err(err) = data.long_term_store.delete_guild(guild_id).await
+    {
+        tracing::warn!(guild_id, "Failed to delete guild memories: {err}");
+    }
+}
@@ -0,0 +1,269 @@
+use serenity::all::{
+    ComponentInteraction, Context, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, Interaction, MessageId, MessageReference,
+};
+
+use crate::{
+    application::chat::chat_service::{TurnRef, continue_turn, delete_turn, regenerate_turn},
+    models::{error::AppError, locale::Locale, memory::MemoryScope},
+    presentation::{
+        access::{check_access, is_silenced},
+        command::command_registry::Data,
+        i18n::{locale_for, messages},
+        outbound::prepare_reply,
+        reply_components::{ReplyAction, ReplyButton, reply_buttons},
+        reply_sender::{delete_messages, edit_reply, remove_components, send_reply},
+        reply_tracker::{Generation, TrackedReply},
+    },
+};
+
+/// 応答に付けたボタンの操作を処理する。コマンドのインタラクションはpoiseが処理する
+pub async fn interaction_create(ctx: Context, interaction: Interaction, data: &Data) {
+    let Interaction::Component(component) = interaction else {
+        return;
+    };
+    let Some(button) = ReplyButton::parse(&component.data.custom_id) else {
+        return;
+    };
+    let locale = locale_for(
+        &ctx,
+        data,
+        component.user.id,
+        component.guild_id,
+        Some(&component.locale),
+    )
+    .await;
+    let messages = messages(locale);
+
+    if component.user.id.get() != button.user_id {
+        respond_ephemeral(&ctx, &component, messages.not_your_reply()).await;
+        return;
+    }
+    let Some(tracked) = data.reply_tracker.get(button.turn_id) else {
+        respond_ephemeral(&ctx, &component, messages.reply_expired()).await;
+        return;
+    };
+    // 削除は利用を止められた後でもできるようにする
+    if button.action != ReplyAction::Delete
+        && is_silenced(
+            &ctx,
+            data,
+            component.guild_id,
+            component.channel_id,
+            component.user.id,
+        )
+        .await
+    {
+        respond_ephemeral(&ctx, &component, messages.action_unavailable()).await;
+        return;
+    }
+    if button.action != ReplyAction::Delete
+        && let Err(err) = check_access(
+            &ctx,
+            data,
+            component.guild_id,
+            component.channel_id,
+            component.user.id,
+            None,
+        )
+        .await
+    {
+        tracing::info!(user_id = %component.user.id, "Denied reply button: {err}");
+        respond_ephemeral(&ctx, &component, messages.error(&err)).await;
+        return;
+    }
+
+    if let Err(err) = component.defer(&ctx.http).await {
+        tracing::warn!("Failed to acknowledge reply button: {err}");
+        return;
+    }
+
+    let turn = TurnRef {
+        channel_id: tracked.channel_id,
+        user_id: tracked.user_id,
+        scope: MemoryScope::for_guild(component.guild_id.map(|id| id.get())),
+        turn_id: button.turn_id,
+    };
+
+    tracing::info!(
+        channel_id = turn.channel_id,
+        user_id = turn.user_id,
+        turn_id = turn.turn_id,
+        action = ?button.action,
+        "Handling reply button"
+    );
+
+    let result = match button.action {
+        ReplyAction::Regenerate => regenerate(&ctx, &component, data, tracked, turn, locale).await,
+        ReplyAction::Continue => {
+            continue_reply(&ctx, &component, data, tracked, turn, locale).await
+        }
+        ReplyAction::Delete => delete(&ctx, &component, data, tracked, turn).await,
+    };
+
+    if let Err(err) = result {
+        tracing::error!(
+            turn_id = turn.turn_id,
+            error = %err,
+            "Failed to handle reply button"
+        );
+        followup_ephemeral(&ctx, &component, messages.error(&err)).await;
+    }
+}
+
+async fn regenerate(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    data: &Data,
+    tracked: TrackedReply,
+    turn: TurnRef,
+    locale: Locale,
+) -> Result<(), AppError> {
+    let _typing = component.channel_id.start_typing(&ctx.http);
+
+    let reply = regenerate_turn(
+        data.ai_client.as_ref(),
+        data.short_term_store.as_ref(),
+        data.long_term_store.as_ref(),
+        data.promotion_queue.as_ref(),
+        turn,
+        tracked.generation.prompt.clone(),
+    )
+    .await?;
+
+    let outbound = prepare_reply(
+        &reply.content,
+        data.mentions
+            .policy_for(component.guild_id.map(|id| id.get())),
+        turn.user_id,
+        &tracked.source_content,
+    );
+    let reply_message_ids = edit_reply(
+        &ctx.http,
+        component.channel_id,
+        &tracked.reply_message_ids,
+        &outbound,
+        &data.attachments,
+        locale,
+        reply_buttons(turn.turn_id, turn.user_id, locale),
+    )
+    .await;
+
+    let generation = Generation {
+        prompt: tracked.generation.prompt.clone(),
+        memories: reply.memories,
+        response: reply.content,
+    };
+    data.reply_tracker.track(
+        turn.turn_id,
+        TrackedReply {
+            reply_message_ids,
+            generation,
+            ..tracked
+        },
+    );
+    Ok(())
+}
+
+async fn continue_reply(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    data: &Data,
+    tracked: TrackedReply,
+    turn: TurnRef,
+    locale: Locale,
+) -> Result<(), AppError> {
+    let _typing = component.channel_id.start_typing(&ctx.http);
+
+    let continuation = continue_turn(
+        data.ai_client.as_ref(),
+        data.short_term_store.as_ref(),
+        data.long_term_store.as_ref(),
+        turn,
+    )
+    .await?;
+
+    let outbound = prepare_reply(
+        &continuation,
+        data.mentions
+            .policy_for(component.guild_id.map(|id| id.get())),
+        turn.user_id,
+        &tracked.source_content,
+    );
+
+    // ボタンは続きの最後のメッセージに移す
+    let last_reply_id = tracked.reply_message_ids.last().copied();
+    if let Some(last_reply_id) = last_reply_id {
+        remove_components(&ctx.http, component.channel_id, last_reply_id).await;
+    }
+    let reference =
+        last_reply_id.map(|id| MessageReference::from((component.channel_id, MessageId::new(id))));
+
+    let sent_ids = send_reply(
+        &ctx.http,
+        component.channel_id,
+        &outbound,
+        &data.attachments,
+        locale,
+        reference,
+        reply_buttons(turn.turn_id, turn.user_id, locale),
+    )
+    .await;
+
+    let mut reply_message_ids = tracked.reply_message_ids.clone();
+    reply_message_ids.extend(sent_ids);
+    let mut generation = tracked.generation.clone();
+    generation.response.push_str(&continuation);
+    data.reply_tracker.track(
+        turn.turn_id,
+        TrackedReply {
+            reply_message_ids,
+            generation,
+            ..tracked
+        },
+    );
+    Ok(())
+}
+
+async fn delete(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    data: &Data,
+    tracked: TrackedReply,
+    turn: TurnRef,
+) -> Result<(), AppError> {
+    data.reply_tracker.remove(turn.turn_id);
+    delete_messages(
+        &ctx.http,
+        component.channel_id,
+        tracked.reply_message_ids.into_iter(),
+    )
+    .await;
+
+    delete_turn(
+        data.short_term_store.as_ref(),
+        data.long_term_store.as_ref(),
+        turn,
+    )
+    .await
+}
+
+async fn respond_ephemeral(ctx: &Context, component: &ComponentInteraction, content: &str) {
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true),
+    );
+    if let Err(err) = component.create_response(&ctx.http, response).await {
+        tracing::warn!("Failed to respond to reply button: {err}");
+    }
+}
+
+async fn followup_ephemeral(ctx: &Context, component: &ComponentInteraction, content: &str) {
+    let followup = CreateInteractionResponseFollowup::new()
+        .content(content)
+        .ephemeral(true);
+    if let Err(err) = component.create_followup(&ctx.http, followup).await {
+        tracing::warn!("Failed to send reply button followup: {err}");
+    }
+}
@@ -1,34 +1,155 @@
 use serenity::all::{Context, Message};
 
 use crate::{
-    application::{
-        chat::chat_service::process_message,
-        traits::{
-            ai_client::AIClient, long_term_store::LongTermStore, short_term_store::ShortTermStore,
-        },
+    application::chat::{
+        chat_service::{ChatRequest, ChatResponse, process_message},
+        prompt_envelope::{ReferencedMessage, wrap_user_message},
+    },
+    models::{
+        locale::Locale,
+        memory::{MemoryScope, TokenUsage},
     },
-    shared::discord_utils::split_message,
+    presentation::{
+        access::{check_access, is_silenced},
+        auto_response::{acquire_cooldown, should_auto_respond},
+        command::command_registry::Data,
+        direct_message::direct_message_allowed,
+        i18n::{locale_for, messages},
+        metadata::collect_metadata,
+        outbound::{OutboundReply, prepare_reply},
+        reply_components::reply_buttons,
+        reply_sender::send_reply,
+        reply_tracker::{Generation, TrackedReply},
+    },
+    shared::metrics::metrics,
 };
 
-pub async fn message(
-    ctx: Context,
-    new_message: Message,
-    ai_client: &dyn AIClient,
-    short_term_store: &dyn ShortTermStore,
-    long_term_store: &dyn LongTermStore,
-) {
+/// 返信チェーンを遡る最大件数
+const MAX_REPLY_CHAIN_DEPTH: usize = 5;
+
+pub async fn message(ctx: Context, new_message: Message, data: &Data) {
     if new_message.author.bot {
         return;
     }
 
+    if is_silenced(
+        &ctx,
+        data,
+        new_message.guild_id,
+        new_message.channel_id,
+        new_message.author.id,
+    )
+    .await
+    {
+        tracing::debug!(
+            channel_id = %new_message.channel_id,
+            user_id = %new_message.author.id,
+            "Ignored message from blocked user or muted channel"
+        );
+        return;
+    }
+
     let bot_id = ctx.cache.current_user().id;
+    let is_direct_message = new_message.guild_id.is_none();
+
     let mentioned = new_message.mentions.iter().any(|u| u.id == bot_id);
+    let auto_response = !is_direct_message && !mentioned;
 
-    if !mentioned {
+    // DMと自動応答チャンネルではメンション不要
+    if is_direct_message {
+        if !direct_message_allowed(&ctx, data, new_message.author.id).await {
+            return;
+        }
+    } else if auto_response && !should_auto_respond(&new_message, data, bot_id).await {
         return;
     }
 
-    let message = new_message
+    if let Err(err) = check_access(
+        &ctx,
+        data,
+        new_message.guild_id,
+        new_message.channel_id,
+        new_message.author.id,
+        new_message.member.as_deref(),
+    )
+    .await
+    {
+        metrics().record_error(err.kind());
+        tracing::info!(
+            channel_id = %new_message.channel_id,
+            user_id = %new_message.author.id,
+            "Ignored message: {err}"
+        );
+        // 自動応答チャンネルでは黙って無視し、呼びかけられた時だけ理由を返す
+        if mentioned {
+            let locale = locale_for(
+                &ctx,
+                data,
+                new_message.author.id,
+                new_message.guild_id,
+                None,
+            )
+            .await;
+            if let Err(err) = new_message
+                .reply(&ctx.http, messages(locale).error(&err))
+                .await
+            {
+                tracing::warn!("Failed to send permission error: {err}");
+            }
+        }
+        return;
+    }
+
+    // 拒否される発言でクールダウンを使うと、他のユーザーへの自動応答まで止まってしまう
+    if auto_response && !acquire_cooldown(&new_message, data) {
+        return;
+    }
+
+    let Some(GeneratedReply {
+        outbound,
+        generation,
+        locale,
+    }) = generate_reply(&ctx, &new_message, data).await
+    else {
+        return;
+    };
+
+    let reply_message_ids = send_reply(
+        &ctx.http,
+        new_message.channel_id,
+        &outbound,
+        &data.attachments,
+        locale,
+        Some((&new_message).into()),
+        reply_buttons(new_message.id.get(), new_message.author.id.get(), locale),
+    )
+    .await;
+
+    data.reply_tracker.track(
+        new_message.id.get(),
+        TrackedReply {
+            channel_id: new_message.channel_id.get(),
+            user_id: new_message.author.id.get(),
+            source_content: new_message.content.clone(),
+            reply_message_ids,
+            generation,
+        },
+    );
+}
+
+pub struct GeneratedReply {
+    pub outbound: OutboundReply,
+    pub generation: Generation,
+    /// 添付の案内やボタンに使う、発言者の表示言語
+    pub locale: Locale,
+}
+
+/// メッセージに対する応答を生成する。本文が空なら `None`
+///
+/// 短期記憶のターンはメッセージIDで管理するため、編集された発言に対して呼ぶと前回のターンを置き換える。
+pub async fn generate_reply(ctx: &Context, msg: &Message, data: &Data) -> Option<GeneratedReply> {
+    let bot_id = ctx.cache.current_user().id;
+    let message = msg
         .content
         .replace(&format!("<@{}>", bot_id), "")
         .replace(&format!("<@!{}>", bot_id), "")
@@ -36,23 +157,33 @@ pub async fn message(
         .to_string();
 
     if message.is_empty() {
-        return;
+        return None;
     }
 
-    let content = construct_metadata(&ctx, &new_message, message);
+    let metadata = collect_metadata(&ctx.cache, msg.guild_id, msg.channel_id, &msg.author);
+    let reply_chain = collect_reply_chain(ctx, msg).await;
+    let content = wrap_user_message(&metadata, &reply_chain, &message);
 
-    let _typing = new_message.channel_id.start_typing(&ctx.http);
+    let _typing = msg.channel_id.start_typing(&ctx.http);
 
-    let channel_id = new_message.channel_id.get();
-    let user_id = new_message.author.id.get();
+    let channel_id = msg.channel_id.get();
+    let user_id = msg.author.id.get();
+    let locale = locale_for(ctx, data, msg.author.id, msg.guild_id, None).await;
 
-    let reply = match process_message(
-        ai_client,
-        short_term_store,
-        long_term_store,
+    let request = ChatRequest {
         channel_id,
         user_id,
-        content,
+        user_message: content.clone(),
+        scope: MemoryScope::for_guild(msg.guild_id.map(|id| id.get())),
+        turn_id: msg.id.get(),
+    };
+
+    let reply = match process_message(
+        data.ai_client.as_ref(),
+        data.short_term_store.as_ref(),
+        data.long_term_store.as_ref(),
+        data.promotion_queue.as_ref(),
+        request,
     )
     .await
     {
@@ -64,56 +195,66 @@ pub async fn message(
                 error = %err,
                 "Failed to process mention message"
             );
-            err.user_facing_message().to_string()
+            ChatResponse {
+                content: messages(locale).error(&err).to_string(),
+                memories: Vec::new(),
+                usage: TokenUsage::default(),
+                context: Vec::new(),
+            }
         }
     };
 
-    let chunks = split_message(&reply);
-    for chunk in &chunks {
-        if let Err(e) = new_message.channel_id.say(&ctx.http, *chunk).await {
-            tracing::error!("Error sending message: {:?}", e);
-            break;
-        }
-    }
+    let outbound = prepare_reply(
+        &reply.content,
+        data.mentions.policy_for(msg.guild_id.map(|id| id.get())),
+        user_id,
+        &msg.content,
+    );
+    Some(GeneratedReply {
+        outbound,
+        generation: Generation {
+            prompt: content,
+            memories: reply.memories,
+            response: reply.content,
+        },
+        locale,
+    })
 }
 
-fn construct_metadata(ctx: &Context, msg: &Message, content: String) -> String {
-    let mut guild_name = "DM".to_string();
-    let mut channel_name = msg.channel_id.to_string();
-    let mut category_name = "None".to_string();
+/// ユーザーが返信した先のメッセージを遡り、古い順に返す
+///
+/// 直近の1件はゲートウェイから届いた `referenced_message` を使い、それより前はキャッシュかAPIから取得する。
+async fn collect_reply_chain(ctx: &Context, msg: &Message) -> Vec<ReferencedMessage> {
+    let mut chain = Vec::new();
+    let mut current = msg.referenced_message.as_deref().cloned();
 
-    if let Some(guild_id) = msg.guild_id
-        && let Some(guild) = ctx.cache.guild(guild_id)
-    {
-        guild_name = guild.name.clone();
+    while let Some(referenced) = current.take() {
+        chain.push(ReferencedMessage {
+            author_name: referenced.author.name.clone(),
+            author_id: referenced.author.id.get(),
+            content: referenced.content.clone(),
+        });
 
-        if let Some(channel) = guild.channels.get(&msg.channel_id) {
-            channel_name = channel.name.clone();
+        if chain.len() >= MAX_REPLY_CHAIN_DEPTH {
+            break;
+        }
+
+        let Some(reference) = referenced.message_reference else {
+            break;
+        };
+        let Some(message_id) = reference.message_id else {
+            break;
+        };
 
-            if let Some(parent_id) = channel.parent_id {
-                category_name = guild
-                    .channels
-                    .get(&parent_id)
-                    .map(|cat| cat.name.clone())
-                    .unwrap_or_else(|| "None".to_string());
+        current = match reference.channel_id.message(ctx, message_id).await {
+            Ok(message) => Some(message),
+            Err(err) => {
+                tracing::debug!("Failed to fetch referenced message {message_id}: {err}");
+                None
             }
-        }
+        };
     }
 
-    let guild_id_str = msg
-        .guild_id
-        .map(|id| id.to_string())
-        .unwrap_or_else(|| "0".to_string());
-
-    format!(
-        "<metadata>\nGuild: {} ({})\nChannel: {} > {} ({})\nUser: {} ({})\n</metadata>\n\n<message>{}</message>",
-        guild_name,
-        guild_id_str,
-        category_name,
-        channel_name,
-        msg.channel_id,
-        msg.author.name,
-        msg.author.id,
-        content
-    )
+    chain.reverse();
+    chain
 }
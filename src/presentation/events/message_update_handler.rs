@@ -0,0 +1,98 @@
+use serenity::all::{Context, MessageUpdateEvent};
+
+use crate::presentation::{
+    access::{check_access, is_silenced},
+    command::command_registry::{COMMAND_PREFIX, Data},
+    events::message_handler::{GeneratedReply, generate_reply},
+    reply_components::reply_buttons,
+    reply_sender::edit_reply,
+    reply_tracker::TrackedReply,
+};
+
+/// ボットが応答した発言が編集されたら、応答を作り直して元の返信を書き換える
+pub async fn message_update(ctx: Context, event: MessageUpdateEvent, data: &Data) {
+    // 本文の変更を伴わない更新（埋め込みの展開など）は無視する
+    let Some(new_content) = event.content.as_deref() else {
+        return;
+    };
+    // `w!chat` の編集はpoiseの編集追跡が処理する
+    if new_content.trim_start().starts_with(COMMAND_PREFIX) {
+        return;
+    }
+    let Some(tracked) = data.reply_tracker.get(event.id.get()) else {
+        return;
+    };
+    if tracked.source_content == new_content {
+        return;
+    }
+
+    let message = match event.channel_id.message(&ctx, event.id).await {
+        Ok(message) => message,
+        Err(err) => {
+            tracing::warn!(message_id = %event.id, "Failed to fetch edited message: {err}");
+            return;
+        }
+    };
+
+    if is_silenced(
+        &ctx,
+        data,
+        message.guild_id,
+        message.channel_id,
+        message.author.id,
+    )
+    .await
+    {
+        return;
+    }
+    if let Err(err) = check_access(
+        &ctx,
+        data,
+        message.guild_id,
+        message.channel_id,
+        message.author.id,
+        message.member.as_deref(),
+    )
+    .await
+    {
+        tracing::info!(message_id = %event.id, "Ignored edited message: {err}");
+        return;
+    }
+
+    tracing::info!(
+        channel_id = tracked.channel_id,
+        user_id = tracked.user_id,
+        message_id = %event.id,
+        "Regenerating reply for edited message"
+    );
+
+    let Some(GeneratedReply {
+        outbound,
+        generation,
+        locale,
+    }) = generate_reply(&ctx, &message, data).await
+    else {
+        return;
+    };
+
+    let reply_message_ids = edit_reply(
+        &ctx.http,
+        message.channel_id,
+        &tracked.reply_message_ids,
+        &outbound,
+        &data.attachments,
+        locale,
+        reply_buttons(event.id.get(), tracked.user_id, locale),
+    )
+    .await;
+
+    data.reply_tracker.track(
+        event.id.get(),
+        TrackedReply {
+            source_content: message.content.clone(),
+            reply_message_ids,
+            generation,
+            ..tracked
+        },
+    );
+}
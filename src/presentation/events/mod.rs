@@ -1,2 +1,6 @@
+pub mod guild_handler;
+pub mod interaction_handler;
 pub mod message_handler;
+pub mod message_update_handler;
+pub mod reaction_handler;
 pub mod ready_handler;
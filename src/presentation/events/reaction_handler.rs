@@ -0,0 +1,91 @@
+use serenity::all::{Context, Reaction, ReactionType};
+
+use crate::{
+    application::chat::chat_service::current_timestamp,
+    models::feedback::{Feedback, Rating},
+    presentation::command::command_registry::Data,
+};
+
+/// 応答に付いた👍/👎を評価として記録する
+pub async fn reaction_add(ctx: Context, reaction: Reaction, data: &Data) {
+    let Some((rater_id, rating)) = rated_by(&ctx, &reaction) else {
+        return;
+    };
+    let Some((turn_id, tracked)) = data.reply_tracker.find_by_reply(reaction.message_id.get())
+    else {
+        return;
+    };
+
+    let feedback = Feedback {
+        turn_id,
+        guild_id: reaction.guild_id.map(|id| id.get()),
+        channel_id: tracked.channel_id,
+        requester_id: tracked.user_id,
+        rater_id,
+        rating,
+        prompt: tracked.generation.prompt,
+        memories: tracked.generation.memories,
+        model: data.model_name.clone(),
+        instruction_version: data.instruction_version.clone(),
+        response: tracked.generation.response,
+        rated_at: current_timestamp(),
+    };
+
+    if let Err(err) = data.feedback_store.record(feedback).await {
+        tracing::error!(turn_id, rater_id, "Failed to record feedback: {err}");
+        return;
+    }
+    tracing::info!(turn_id, rater_id, ?rating, "Recorded feedback");
+}
+
+/// リアクションが外されたら、その評価を取り消す
+pub async fn reaction_remove(ctx: Context, reaction: Reaction, data: &Data) {
+    let Some((rater_id, rating)) = rated_by(&ctx, &reaction) else {
+        return;
+    };
+    let Some((turn_id, _)) = data.reply_tracker.find_by_reply(reaction.message_id.get()) else {
+        return;
+    };
+
+    if let Err(err) = data.feedback_store.retract(turn_id, rater_id, rating).await {
+        tracing::error!(turn_id, rater_id, "Failed to retract feedback: {err}");
+    }
+}
+
+/// ボット自身以外が付けた👍/👎なら、付けたユーザーと評価を返す
+fn rated_by(ctx: &Context, reaction: &Reaction) -> Option<(u64, Rating)> {
+    let rating = rating_for(&reaction.emoji)?;
+    let user_id = reaction.user_id?;
+    if user_id == ctx.cache.current_user().id {
+        return None;
+    }
+    Some((user_id.get(), rating))
+}
+
+/// 肌の色の修飾子が付いていても同じ評価として扱う
+fn rating_for(emoji: &ReactionType) -> Option<Rating> {
+    let ReactionType::Unicode(emoji) = emoji else {
+        return None;
+    };
+    if emoji.starts_with('👍') {
+        Some(Rating::Good)
+    } else if emoji.starts_with('👎') {
+        Some(Rating::Bad)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbs_map_to_ratings() {
+        let unicode = |s: &str| ReactionType::Unicode(s.to_string());
+        assert_eq!(rating_for(&unicode("👍")), Some(Rating::Good));
+        assert_eq!(rating_for(&unicode("👍🏽")), Some(Rating::Good));
+        assert_eq!(rating_for(&unicode("👎")), Some(Rating::Bad));
+        assert_eq!(rating_for(&unicode("🎉")), None);
+    }
+}
@@ -2,34 +2,96 @@ use std::sync::Arc;
 
 use serenity::{
     async_trait,
-    model::{channel::Message, gateway::Ready},
+    model::{
+        application::Interaction,
+        channel::{Message, Reaction},
+        event::MessageUpdateEvent,
+        gateway::Ready,
+        guild::{Guild, UnavailableGuild},
+    },
     prelude::*,
 };
+use tracing::Instrument;
 
-use crate::{
-    application::traits::{
-        ai_client::AIClient, long_term_store::LongTermStore, short_term_store::ShortTermStore,
-    },
-    presentation::events::*,
-};
+use crate::presentation::{command::command_registry::Data, events::*};
 
 pub struct Handler {
-    pub ai_client: Arc<dyn AIClient>,
-    pub short_term_store: Arc<dyn ShortTermStore>,
-    pub long_term_store: Arc<dyn LongTermStore>,
+    pub data: Arc<Data>,
 }
 
 #[async_trait]
 impl EventHandler for Handler {
     async fn message(&self, ctx: Context, new_message: Message) {
-        message_handler::message(
-            ctx,
-            new_message,
-            self.ai_client.as_ref(),
-            self.short_term_store.as_ref(),
-            self.long_term_store.as_ref(),
-        )
-        .await;
+        let Some(_in_flight) = self.data.in_flight.begin() else {
+            return;
+        };
+        let span = tracing::info_span!(
+            "message",
+            channel_id = new_message.channel_id.get(),
+            user_id = new_message.author.id.get(),
+            guild_id = new_message.guild_id.map(|id| id.get()),
+            message_id = new_message.id.get(),
+        );
+        message_handler::message(ctx, new_message, &self.data)
+            .instrument(span)
+            .await;
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        _new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        let Some(_in_flight) = self.data.in_flight.begin() else {
+            return;
+        };
+        let span = tracing::info_span!(
+            "message_update",
+            channel_id = event.channel_id.get(),
+            user_id = event.author.as_ref().map(|u| u.id.get()),
+            guild_id = event.guild_id.map(|id| id.get()),
+            message_id = event.id.get(),
+        );
+        message_update_handler::message_update(ctx, event, &self.data)
+            .instrument(span)
+            .await;
+    }
+
+    async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
+        guild_handler::guild_create(ctx, guild, is_new, &self.data).await;
+    }
+
+    async fn guild_delete(&self, _ctx: Context, incomplete: UnavailableGuild, full: Option<Guild>) {
+        guild_handler::guild_delete(incomplete, full, &self.data).await;
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Some(_in_flight) = self.data.in_flight.begin() else {
+            return;
+        };
+        let span = match &interaction {
+            Interaction::Component(component) => tracing::info_span!(
+                "component",
+                custom_id = component.data.custom_id.as_str(),
+                channel_id = component.channel_id.get(),
+                user_id = component.user.id.get(),
+                guild_id = component.guild_id.map(|id| id.get()),
+            ),
+            _ => tracing::Span::none(),
+        };
+        interaction_handler::interaction_create(ctx, interaction, &self.data)
+            .instrument(span)
+            .await;
+    }
+
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        reaction_handler::reaction_add(ctx, add_reaction, &self.data).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, removed_reaction: Reaction) {
+        reaction_handler::reaction_remove(ctx, removed_reaction, &self.data).await;
     }
 
     async fn ready(&self, ctx: Context, data_about_bot: Ready) {
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use serenity::all::{ConnectionStage, ShardManager};
+
+use crate::{
+    models::{health::HealthReport, locale::Locale},
+    presentation::{command::command_registry::Data, i18n::messages},
+};
+
+/// Discordのゲートウェイとの接続状態
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewayStatus {
+    pub shards: usize,
+    pub connected: usize,
+    /// 接続中のシャードのハートビートの平均応答時間
+    pub latency_ms: Option<u64>,
+}
+
+impl GatewayStatus {
+    pub fn is_connected(&self) -> bool {
+        self.shards > 0 && self.connected == self.shards
+    }
+}
+
+pub async fn gateway_status(shard_manager: &ShardManager) -> GatewayStatus {
+    let runners = shard_manager.runners.lock().await;
+    let latencies: Vec<Duration> = runners.values().filter_map(|r| r.latency).collect();
+
+    GatewayStatus {
+        shards: runners.len(),
+        connected: runners
+            .values()
+            .filter(|r| r.stage == ConnectionStage::Connected)
+            .count(),
+        latency_ms: (!latencies.is_empty()).then(|| {
+            let total: Duration = latencies.iter().sum();
+            (total / latencies.len() as u32).as_millis() as u64
+        }),
+    }
+}
+
+/// 依存先の状態と稼働時間
+pub async fn health_report(data: &Data) -> HealthReport {
+    data.health_checker
+        .check(data.short_term_store.as_ref(), data.started_at.elapsed())
+        .await
+}
+
+/// 稼働時間を表示する単位。1分未満は秒で表す
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uptime {
+    Seconds(u64),
+    Minutes(u64),
+    Hours { hours: u64, minutes: u64 },
+    Days { days: u64, hours: u64, minutes: u64 },
+}
+
+impl Uptime {
+    pub fn new(uptime: Duration) -> Self {
+        let secs = uptime.as_secs();
+        let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+
+        if secs < 60 {
+            Self::Seconds(secs)
+        } else if days > 0 {
+            Self::Days {
+                days,
+                hours,
+                minutes,
+            }
+        } else if hours > 0 {
+            Self::Hours { hours, minutes }
+        } else {
+            Self::Minutes(minutes)
+        }
+    }
+}
+
+/// `1日 2時間 3分` の形式
+pub fn format_uptime(uptime: Duration, locale: Locale) -> String {
+    messages(locale).uptime(Uptime::new(uptime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_uptime_with_largest_units() {
+        assert_eq!(format_uptime(Duration::from_secs(42), Locale::Ja), "42秒");
+        assert_eq!(
+            format_uptime(Duration::from_secs(5 * 60 + 7), Locale::Ja),
+            "5分"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(3 * 3_600 + 60), Locale::Ja),
+            "3時間 1分"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(86_400 + 2 * 3_600 + 3 * 60), Locale::Ja),
+            "1日 2時間 3分"
+        );
+    }
+
+    #[test]
+    fn formats_uptime_in_english() {
+        assert_eq!(
+            format_uptime(Duration::from_secs(60), Locale::En),
+            "1 minute"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(86_400 + 2 * 3_600), Locale::En),
+            "1 day 2 hours 0 minutes"
+        );
+    }
+}
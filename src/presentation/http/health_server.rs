@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use serde::Serialize;
+use serenity::all::ShardManager;
+
+use crate::{
+    models::health::HealthReport,
+    presentation::{
+        command::command_registry::Data,
+        health::{GatewayStatus, gateway_status, health_report},
+    },
+};
+
+#[derive(Clone)]
+struct HealthState {
+    data: Arc<Data>,
+    shard_manager: Arc<ShardManager>,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    healthy: bool,
+    gateway: GatewayStatus,
+    #[serde(flatten)]
+    report: Option<HealthReport>,
+}
+
+/// コンテナのオーケストレーター向けに `/healthz` と `/readyz` を公開する
+pub async fn serve(address: &str, data: Arc<Data>, shard_manager: Arc<ShardManager>) -> Result<()> {
+    let app = Router::new()
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness))
+        .with_state(HealthState {
+            data,
+            shard_manager,
+        });
+
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to bind health server to {address}"))?;
+    tracing::info!("Health server listening on {address}");
+
+    axum::serve(listener, app)
+        .await
+        .context("Health server stopped")
+}
+
+/// ゲートウェイに接続できていれば生きているとみなす。依存先の障害で再起動させないため、外部には問い合わせない
+async fn liveness(State(state): State<HealthState>) -> (StatusCode, Json<HealthResponse>) {
+    let gateway = gateway_status(&state.shard_manager).await;
+    respond(gateway.is_connected(), gateway, None)
+}
+
+/// ゲートウェイと全ての依存先が使える時だけ応答できる状態とみなす
+async fn readiness(State(state): State<HealthState>) -> (StatusCode, Json<HealthResponse>) {
+    let (gateway, report) = tokio::join!(
+        gateway_status(&state.shard_manager),
+        health_report(&state.data)
+    );
+    let healthy = gateway.is_connected() && report.is_healthy();
+    respond(healthy, gateway, Some(report))
+}
+
+fn respond(
+    healthy: bool,
+    gateway: GatewayStatus,
+    report: Option<HealthReport>,
+) -> (StatusCode, Json<HealthResponse>) {
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(HealthResponse {
+            healthy,
+            gateway,
+            report,
+        }),
+    )
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{Router, extract::State, http::header, response::IntoResponse, routing::get};
+
+use crate::{presentation::command::command_registry::Data, shared::metrics::metrics};
+
+/// Prometheusがスクレイプする `/metrics` を公開する
+pub async fn serve(address: &str, data: Arc<Data>) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(export))
+        .with_state(data);
+
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to bind metrics server to {address}"))?;
+    tracing::info!("Metrics server listening on {address}");
+
+    axum::serve(listener, app)
+        .await
+        .context("Metrics server stopped")
+}
+
+async fn export(State(data): State<Arc<Data>>) -> impl IntoResponse {
+    // 短期記憶の量はスクレイプの度に数え直す
+    let stats = data.short_term_store.stats().await;
+    metrics().short_term_channels.set(stats.channels as i64);
+    metrics().short_term_messages.set(stats.messages as i64);
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().encode(),
+    )
+}
@@ -0,0 +1,3 @@
+pub mod chat_api_server;
+pub mod health_server;
+pub mod metrics_server;
@@ -0,0 +1,303 @@
+use crate::{
+    application::feedback::feedback_service::FeedbackSummary,
+    models::{error::AppError, locale::Locale, settings::ChannelMode},
+    presentation::{health::Uptime, i18n::Messages, reply_components::ReplyAction},
+    shared::discord_utils::AttachmentNotes,
+};
+
+pub struct English;
+
+fn language_name(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ja => "Japanese",
+        Locale::En => "English",
+    }
+}
+
+fn plural(count: u64, unit: &str) -> String {
+    if count == 1 {
+        format!("{count} {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+/// コマンドの英語の説明。キーはサブコマンドを空白で区切った名前
+pub(super) fn command_description(path: &str) -> Option<&'static str> {
+    let description = match path {
+        "chat" => "Talk with the bot",
+        "health" => "Check the status of the bot and its dependencies",
+        "language" => "Choose the language of the bot's messages",
+        "help" => "List the available commands",
+        "autoreply" => "Manage channels where the bot replies without a mention",
+        "autoreply on" => "Reply to every message in a channel",
+        "autoreply off" => "Reply in a channel only when mentioned",
+        "autoreply list" => "List channels with auto-reply enabled",
+        "feedback" => "Review ratings of the bot's replies",
+        "feedback stats" => "👍/👎 totals per model and system prompt version",
+        "access" => "Manage who can use the bot and where",
+        "access show" => "Show the current access rules",
+        "access allow" => "Add to the allow list (only matches can use the bot)",
+        "access deny" => "Add to the deny list, which overrides the allow list",
+        "access remove" => "Remove from both the allow and the deny list",
+        "access admin-role" => "Set roles that can use admin commands",
+        "admin" => "Silence the bot for specific users or channels",
+        "admin block" => "Ignore every message and command from a user",
+        "admin unblock" => "Unblock a user",
+        "admin mute-channel" => "Stop replying in a channel entirely",
+        "admin unmute-channel" => "Unmute a channel",
+        "admin blocked" => "List blocked users",
+        "admin muted" => "List muted channels",
+        _ => return None,
+    };
+    Some(description)
+}
+
+pub(super) fn parameter_description(path: &str, parameter: &str) -> Option<&'static str> {
+    let description = match (path, parameter) {
+        ("chat", "prompt") => "Prompt",
+        ("language", "language") => "Language (omit to show the current setting)",
+        ("access allow" | "access deny" | "access remove", "user") => "User",
+        ("access allow" | "access deny" | "access remove" | "access admin-role", "role") => "Role",
+        ("access allow" | "access deny" | "access remove", "channel") => "Channel or category",
+        ("access admin-role", "enabled") => "Make the role an admin (false to remove)",
+        ("admin block" | "admin unblock", "user") => "Target user",
+        (
+            "autoreply on" | "autoreply off" | "admin mute-channel" | "admin unmute-channel",
+            "channel",
+        ) => "Target channel (defaults to this channel)",
+        _ => return None,
+    };
+    Some(description)
+}
+
+impl Messages for English {
+    fn attachment_notes(&self) -> AttachmentNotes {
+        AttachmentNotes {
+            code_attached: |filename| format!("(code attached as `{filename}`)"),
+            full_text_attached: "📎 See the attached file for the full reply.",
+        }
+    }
+
+    fn button_label(&self, action: ReplyAction) -> &'static str {
+        match action {
+            ReplyAction::Regenerate => "Regenerate",
+            ReplyAction::Continue => "Continue",
+            ReplyAction::Delete => "Delete",
+        }
+    }
+
+    fn not_your_reply(&self) -> &'static str {
+        "Only the person who asked can do this."
+    }
+
+    fn reply_expired(&self) -> &'static str {
+        "This reply can no longer be changed."
+    }
+
+    fn action_unavailable(&self) -> &'static str {
+        "This action is not available right now."
+    }
+
+    fn direct_messages_unavailable(&self) -> &'static str {
+        "This is not available in DMs."
+    }
+
+    fn error(&self, err: &AppError) -> &'static str {
+        match err {
+            AppError::AIGeneration(_) => "Failed to generate a response.",
+            AppError::Embedding(_) => "Failed to process the text.",
+            AppError::Store(_) => "Failed to search memories.",
+            AppError::Discord(_) => "Failed to communicate with Discord.",
+            AppError::Config(_) => "Failed to load the configuration.",
+            AppError::ConversationNotFound(_) => "The conversation was not found.",
+            AppError::PermissionDenied { .. } => "You do not have permission to do that.",
+            AppError::Internal(_) => "An unexpected error occurred.",
+        }
+    }
+
+    fn health_status(&self, healthy: bool) -> &'static str {
+        if healthy {
+            "**Status: healthy**"
+        } else {
+            "**Status: degraded**"
+        }
+    }
+
+    fn health_gateway(&self, connected: usize, shards: usize, latency_ms: Option<u64>) -> String {
+        let latency = latency_ms
+            .map(|ms| format!("{ms}ms"))
+            .unwrap_or_else(|| "measuring".to_string());
+        format!("Discord: {connected}/{shards} shards connected ({latency})")
+    }
+
+    fn health_short_term(&self, channels: usize, messages: usize) -> String {
+        format!("Short-term memory: {channels} channels / {messages} messages")
+    }
+
+    fn health_uptime(&self, uptime: &str) -> String {
+        format!("Uptime: {uptime}")
+    }
+
+    fn uptime(&self, uptime: Uptime) -> String {
+        match uptime {
+            Uptime::Seconds(secs) => plural(secs, "second"),
+            Uptime::Minutes(minutes) => plural(minutes, "minute"),
+            Uptime::Hours { hours, minutes } => {
+                format!("{} {}", plural(hours, "hour"), plural(minutes, "minute"))
+            }
+            Uptime::Days {
+                days,
+                hours,
+                minutes,
+            } => format!(
+                "{} {} {}",
+                plural(days, "day"),
+                plural(hours, "hour"),
+                plural(minutes, "minute")
+            ),
+        }
+    }
+
+    fn feedback_empty(&self) -> &'static str {
+        "There are no ratings yet."
+    }
+
+    fn feedback_line(&self, s: &FeedbackSummary) -> String {
+        format!(
+            "- `{}` / prompt `{}`: 👍 {} 👎 {} ({:.0}%) <t:{}:d>–<t:{}:d>",
+            s.model,
+            s.instruction_version,
+            s.good,
+            s.bad,
+            s.approval_rate() * 100.0,
+            s.first_rated_at,
+            s.last_rated_at,
+        )
+    }
+
+    fn feedback_summary(&self, model: &str, instruction_version: &str, lines: &str) -> String {
+        format!("Ratings (current: `{model}` / prompt `{instruction_version}`)\n{lines}")
+    }
+
+    fn none(&self) -> &'static str {
+        "None"
+    }
+
+    fn access_rules(&self, allow: &str, deny: &str, admin_roles: &str) -> String {
+        format!(
+            "**Allow list** (everyone may use the bot when empty)\n{allow}\n**Deny list**\n{deny}\n**Bot admin roles**: {admin_roles}"
+        )
+    }
+
+    fn access_users(&self, mentions: &str) -> String {
+        format!("- Users: {mentions}")
+    }
+
+    fn access_roles(&self, mentions: &str) -> String {
+        format!("- Roles: {mentions}")
+    }
+
+    fn access_channels(&self, mentions: &str) -> String {
+        format!("- Channels: {mentions}")
+    }
+
+    fn access_target_required(&self) -> &'static str {
+        "Specify a user, a role or a channel."
+    }
+
+    fn access_allowed(&self, names: &str) -> String {
+        format!("Added {names} to the allow list.")
+    }
+
+    fn access_denied(&self, names: &str) -> String {
+        format!("Added {names} to the deny list.")
+    }
+
+    fn access_removed(&self, names: &str) -> String {
+        format!("Removed {names} from the lists.")
+    }
+
+    fn admin_role_changed(&self, role_id: u64, enabled: bool) -> String {
+        if enabled {
+            format!("<@&{role_id}> is now a bot admin role.")
+        } else {
+            format!("<@&{role_id}> is no longer a bot admin role.")
+        }
+    }
+
+    fn autoreply_none(&self) -> &'static str {
+        "No channels have auto-reply enabled."
+    }
+
+    fn autoreply_channels(&self, lines: &str) -> String {
+        format!("Channels with auto-reply enabled:\n{lines}")
+    }
+
+    fn channel_mode_changed(&self, channel_id: u64, mode: ChannelMode) -> String {
+        match mode {
+            ChannelMode::AutoRespond => {
+                format!("The bot now replies in <#{channel_id}> without a mention.")
+            }
+            ChannelMode::MentionOnly => {
+                format!("The bot now replies in <#{channel_id}> only when mentioned.")
+            }
+        }
+    }
+
+    fn blocked_none(&self) -> &'static str {
+        "No users are blocked."
+    }
+
+    fn blocked_users(&self, lines: &str) -> String {
+        format!("Blocked users:\n{lines}")
+    }
+
+    fn muted_none(&self) -> &'static str {
+        "No channels are muted."
+    }
+
+    fn muted_channels(&self, lines: &str) -> String {
+        format!("Muted channels:\n{lines}")
+    }
+
+    fn block_changed(&self, user_id: u64, blocked: bool, changed: bool) -> String {
+        match (blocked, changed) {
+            (true, true) => format!("Blocked <@{user_id}>."),
+            (true, false) => format!("<@{user_id}> is already blocked."),
+            (false, true) => format!("Unblocked <@{user_id}>."),
+            (false, false) => format!("<@{user_id}> is not blocked."),
+        }
+    }
+
+    fn mute_changed(&self, channel_id: u64, muted: bool, changed: bool) -> String {
+        match (muted, changed) {
+            (true, true) => format!("The bot will no longer reply in <#{channel_id}>."),
+            (true, false) => format!("<#{channel_id}> is already muted."),
+            (false, true) => format!("Unmuted <#{channel_id}>."),
+            (false, false) => format!("<#{channel_id}> is not muted."),
+        }
+    }
+
+    fn language_current(&self, preferred: Option<Locale>) -> String {
+        match preferred {
+            Some(locale) => format!("Your language is set to {}.", language_name(locale)),
+            None => "Your language follows your Discord language settings.".to_string(),
+        }
+    }
+
+    fn language_changed(&self, preferred: Option<Locale>) -> String {
+        match preferred {
+            Some(locale) => format!("Switched your language to {}.", language_name(locale)),
+            None => "Your language now follows your Discord language settings.".to_string(),
+        }
+    }
+
+    fn help_header(&self) -> &'static str {
+        "**Commands**"
+    }
+
+    fn help_footer(&self) -> &'static str {
+        "Mention the bot or send it a DM to chat. Use the buttons under a reply to regenerate, continue or delete it."
+    }
+}
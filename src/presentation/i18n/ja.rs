@@ -0,0 +1,239 @@
+use crate::{
+    application::feedback::feedback_service::FeedbackSummary,
+    models::{error::AppError, locale::Locale, settings::ChannelMode},
+    presentation::{health::Uptime, i18n::Messages, reply_components::ReplyAction},
+    shared::discord_utils::AttachmentNotes,
+};
+
+pub struct Japanese;
+
+fn language_name(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Ja => "日本語",
+        Locale::En => "英語",
+    }
+}
+
+impl Messages for Japanese {
+    fn attachment_notes(&self) -> AttachmentNotes {
+        AttachmentNotes {
+            code_attached: |filename| format!("（コードは `{filename}` に添付）"),
+            full_text_attached: "📎 全文は添付ファイルをご覧ください。",
+        }
+    }
+
+    fn button_label(&self, action: ReplyAction) -> &'static str {
+        match action {
+            ReplyAction::Regenerate => "再生成",
+            ReplyAction::Continue => "続き",
+            ReplyAction::Delete => "削除",
+        }
+    }
+
+    fn not_your_reply(&self) -> &'static str {
+        "この操作は質問した本人のみ行えます。"
+    }
+
+    fn reply_expired(&self) -> &'static str {
+        "この応答は操作できなくなりました。"
+    }
+
+    fn action_unavailable(&self) -> &'static str {
+        "この操作は現在行えません。"
+    }
+
+    fn direct_messages_unavailable(&self) -> &'static str {
+        "DMでは利用できません。"
+    }
+
+    fn error(&self, err: &AppError) -> &'static str {
+        match err {
+            AppError::AIGeneration(_) => "AI応答の生成に失敗しました。",
+            AppError::Embedding(_) => "テキストの処理に失敗しました。",
+            AppError::Store(_) => "記憶の検索に失敗しました。",
+            AppError::Discord(_) => "Discordとの通信に失敗しました。",
+            AppError::Config(_) => "設定の読み込みに失敗しました。",
+            AppError::ConversationNotFound(_) => "会話が見つかりませんでした。",
+            AppError::PermissionDenied { .. } => "権限がありません。",
+            AppError::Internal(_) => "予期しないエラーが発生しました。",
+        }
+    }
+
+    fn health_status(&self, healthy: bool) -> &'static str {
+        if healthy {
+            "**状態: 正常**"
+        } else {
+            "**状態: 異常あり**"
+        }
+    }
+
+    fn health_gateway(&self, connected: usize, shards: usize, latency_ms: Option<u64>) -> String {
+        let latency = latency_ms
+            .map(|ms| format!("{ms}ms"))
+            .unwrap_or_else(|| "計測中".to_string());
+        format!("Discord: {connected}/{shards} シャード接続中（{latency}）")
+    }
+
+    fn health_short_term(&self, channels: usize, messages: usize) -> String {
+        format!("短期記憶: {channels}チャンネル / {messages}件")
+    }
+
+    fn health_uptime(&self, uptime: &str) -> String {
+        format!("稼働時間: {uptime}")
+    }
+
+    fn uptime(&self, uptime: Uptime) -> String {
+        match uptime {
+            Uptime::Seconds(secs) => format!("{secs}秒"),
+            Uptime::Minutes(minutes) => format!("{minutes}分"),
+            Uptime::Hours { hours, minutes } => format!("{hours}時間 {minutes}分"),
+            Uptime::Days {
+                days,
+                hours,
+                minutes,
+            } => format!("{days}日 {hours}時間 {minutes}分"),
+        }
+    }
+
+    fn feedback_empty(&self) -> &'static str {
+        "まだ評価はありません。"
+    }
+
+    fn feedback_line(&self, s: &FeedbackSummary) -> String {
+        format!(
+            "- `{}` / 指示 `{}`: 👍 {} 👎 {}（{:.0}%） <t:{}:d>〜<t:{}:d>",
+            s.model,
+            s.instruction_version,
+            s.good,
+            s.bad,
+            s.approval_rate() * 100.0,
+            s.first_rated_at,
+            s.last_rated_at,
+        )
+    }
+
+    fn feedback_summary(&self, model: &str, instruction_version: &str, lines: &str) -> String {
+        format!("評価の集計（現在: `{model}` / 指示 `{instruction_version}`）\n{lines}")
+    }
+
+    fn none(&self) -> &'static str {
+        "なし"
+    }
+
+    fn access_rules(&self, allow: &str, deny: &str, admin_roles: &str) -> String {
+        format!(
+            "**許可リスト**（空なら全員が利用可能）\n{allow}\n**拒否リスト**\n{deny}\n**ボット管理者ロール**: {admin_roles}"
+        )
+    }
+
+    fn access_users(&self, mentions: &str) -> String {
+        format!("- ユーザー: {mentions}")
+    }
+
+    fn access_roles(&self, mentions: &str) -> String {
+        format!("- ロール: {mentions}")
+    }
+
+    fn access_channels(&self, mentions: &str) -> String {
+        format!("- チャンネル: {mentions}")
+    }
+
+    fn access_target_required(&self) -> &'static str {
+        "ユーザー・ロール・チャンネルのいずれかを指定してください。"
+    }
+
+    fn access_allowed(&self, names: &str) -> String {
+        format!("{names} を許可リストに追加しました。")
+    }
+
+    fn access_denied(&self, names: &str) -> String {
+        format!("{names} を拒否リストに追加しました。")
+    }
+
+    fn access_removed(&self, names: &str) -> String {
+        format!("{names} をリストから取り除きました。")
+    }
+
+    fn admin_role_changed(&self, role_id: u64, enabled: bool) -> String {
+        if enabled {
+            format!("<@&{role_id}> をボット管理者にしました。")
+        } else {
+            format!("<@&{role_id}> をボット管理者から外しました。")
+        }
+    }
+
+    fn autoreply_none(&self) -> &'static str {
+        "自動応答が有効なチャンネルはありません。"
+    }
+
+    fn autoreply_channels(&self, lines: &str) -> String {
+        format!("自動応答が有効なチャンネル:\n{lines}")
+    }
+
+    fn channel_mode_changed(&self, channel_id: u64, mode: ChannelMode) -> String {
+        match mode {
+            ChannelMode::AutoRespond => {
+                format!("<#{channel_id}> ではメンション無しでも応答します。")
+            }
+            ChannelMode::MentionOnly => {
+                format!("<#{channel_id}> ではメンションされた時だけ応答します。")
+            }
+        }
+    }
+
+    fn blocked_none(&self) -> &'static str {
+        "ブロックしているユーザーはいません。"
+    }
+
+    fn blocked_users(&self, lines: &str) -> String {
+        format!("ブロックしているユーザー:\n{lines}")
+    }
+
+    fn muted_none(&self) -> &'static str {
+        "ミュートしているチャンネルはありません。"
+    }
+
+    fn muted_channels(&self, lines: &str) -> String {
+        format!("ミュートしているチャンネル:\n{lines}")
+    }
+
+    fn block_changed(&self, user_id: u64, blocked: bool, changed: bool) -> String {
+        match (blocked, changed) {
+            (true, true) => format!("<@{user_id}> をブロックしました。"),
+            (true, false) => format!("<@{user_id}> は既にブロックされています。"),
+            (false, true) => format!("<@{user_id}> のブロックを解除しました。"),
+            (false, false) => format!("<@{user_id}> はブロックされていません。"),
+        }
+    }
+
+    fn mute_changed(&self, channel_id: u64, muted: bool, changed: bool) -> String {
+        match (muted, changed) {
+            (true, true) => format!("<#{channel_id}> では応答しないようにしました。"),
+            (true, false) => format!("<#{channel_id}> は既にミュートされています。"),
+            (false, true) => format!("<#{channel_id}> のミュートを解除しました。"),
+            (false, false) => format!("<#{channel_id}> はミュートされていません。"),
+        }
+    }
+
+    fn language_current(&self, preferred: Option<Locale>) -> String {
+        match preferred {
+            Some(locale) => format!("表示言語は{}に設定されています。", language_name(locale)),
+            None => "表示言語はDiscordの言語設定に合わせています。".to_string(),
+        }
+    }
+
+    fn language_changed(&self, preferred: Option<Locale>) -> String {
+        match preferred {
+            Some(locale) => format!("表示言語を{}にしました。", language_name(locale)),
+            None => "表示言語をDiscordの言語設定に合わせるようにしました。".to_string(),
+        }
+    }
+
+    fn help_header(&self) -> &'static str {
+        "**コマンド一覧**"
+    }
+
+    fn help_footer(&self) -> &'static str {
+        "メンションするか、DMを送ると会話できます。応答のボタンで再生成・続き・削除ができます。"
+    }
+}
@@ -0,0 +1,240 @@
+mod en;
+mod ja;
+
+use std::sync::Arc;
+
+use serenity::all::{GuildId, UserId};
+
+use crate::{
+    application::feedback::feedback_service::FeedbackSummary,
+    models::{error::AppError, locale::Locale, settings::ChannelMode},
+    presentation::{
+        command::command_registry::{Context, Data},
+        health::Uptime,
+        reply_components::ReplyAction,
+    },
+    shared::discord_utils::AttachmentNotes,
+};
+
+/// 英語の説明を登録するDiscordのロケール。日本語はコマンドの既定の説明に書く
+const ENGLISH_DISCORD_LOCALES: [&str; 2] = ["en-US", "en-GB"];
+
+/// Every piece of text the bot writes to Discord, in one language.
+///
+/// Model output is not covered; its language is decided by the system prompt.
+pub trait Messages: Send + Sync {
+    fn attachment_notes(&self) -> AttachmentNotes;
+    fn button_label(&self, action: ReplyAction) -> &'static str;
+    fn not_your_reply(&self) -> &'static str;
+    fn reply_expired(&self) -> &'static str;
+    fn action_unavailable(&self) -> &'static str;
+    fn direct_messages_unavailable(&self) -> &'static str;
+    /// 利用者に見せるエラーの説明。内部の詳細は含めない
+    fn error(&self, err: &AppError) -> &'static str;
+
+    fn health_status(&self, healthy: bool) -> &'static str;
+    fn health_gateway(&self, connected: usize, shards: usize, latency_ms: Option<u64>) -> String;
+    fn health_short_term(&self, channels: usize, messages: usize) -> String;
+    fn health_uptime(&self, uptime: &str) -> String;
+    fn uptime(&self, uptime: Uptime) -> String;
+
+    fn feedback_empty(&self) -> &'static str;
+    fn feedback_line(&self, summary: &FeedbackSummary) -> String;
+    /// `lines` is the output of `feedback_line`, one summary per line.
+    fn feedback_summary(&self, model: &str, instruction_version: &str, lines: &str) -> String;
+
+    fn none(&self) -> &'static str;
+    fn access_rules(&self, allow: &str, deny: &str, admin_roles: &str) -> String;
+    fn access_users(&self, mentions: &str) -> String;
+    fn access_roles(&self, mentions: &str) -> String;
+    fn access_channels(&self, mentions: &str) -> String;
+    fn access_target_required(&self) -> &'static str;
+    fn access_allowed(&self, names: &str) -> String;
+    fn access_denied(&self, names: &str) -> String;
+    fn access_removed(&self, names: &str) -> String;
+    fn admin_role_changed(&self, role_id: u64, enabled: bool) -> String;
+
+    fn autoreply_none(&self) -> &'static str;
+    fn autoreply_channels(&self, lines: &str) -> String;
+    fn channel_mode_changed(&self, channel_id: u64, mode: ChannelMode) -> String;
+
+    fn blocked_none(&self) -> &'static str;
+    fn blocked_users(&self, lines: &str) -> String;
+    fn muted_none(&self) -> &'static str;
+    fn muted_channels(&self, lines: &str) -> String;
+    fn block_changed(&self, user_id: u64, blocked: bool, changed: bool) -> String;
+    fn mute_changed(&self, channel_id: u64, muted: bool, changed: bool) -> String;
+
+    /// `preferred` is the user's own setting, `None` meaning Discord's locale is used.
+    fn language_current(&self, preferred: Option<Locale>) -> String;
+    fn language_changed(&self, preferred: Option<Locale>) -> String;
+
+    fn help_header(&self) -> &'static str;
+    fn help_footer(&self) -> &'static str;
+}
+
+pub fn messages(locale: Locale) -> &'static dyn Messages {
+    match locale {
+        Locale::Ja => &ja::Japanese,
+        Locale::En => &en::English,
+    }
+}
+
+/// 利用者の設定、インタラクションのロケール、サーバーの優先ロケールから表示言語を選ぶ
+pub async fn locale_for(
+    ctx: &serenity::all::Context,
+    data: &Data,
+    user_id: UserId,
+    guild_id: Option<GuildId>,
+    interaction_locale: Option<&str>,
+) -> Locale {
+    let preferred = data.settings_store.user(user_id.get()).await.locale;
+    let guild_locale = guild_id.and_then(|id| {
+        ctx.cache
+            .guild(id)
+            .map(|guild| guild.preferred_locale.clone())
+    });
+
+    Locale::resolve(preferred, interaction_locale, guild_locale.as_deref())
+}
+
+/// コマンドの表示言語。プレフィックスコマンドにはインタラクションのロケールが無い
+pub async fn command_locale(ctx: Context<'_>) -> Locale {
+    locale_for(
+        ctx.serenity_context(),
+        ctx.data(),
+        ctx.author().id,
+        ctx.guild_id(),
+        ctx.locale(),
+    )
+    .await
+}
+
+/// コマンドと引数に英語の説明を付ける。Discordはクライアントの言語に合わせて表示する
+pub fn localize_commands(commands: &mut [poise::Command<Arc<Data>, anyhow::Error>]) {
+    localize_commands_under("", commands);
+}
+
+fn localize_commands_under(
+    parent: &str,
+    commands: &mut [poise::Command<Arc<Data>, anyhow::Error>],
+) {
+    for command in commands {
+        let path = format!("{parent}{}", command.name);
+
+        if let Some(description) = en::command_description(&path) {
+            for locale in ENGLISH_DISCORD_LOCALES {
+                command
+                    .description_localizations
+                    .insert(locale.to_string(), description.to_string());
+            }
+        }
+        for parameter in &mut command.parameters {
+            if let Some(description) = en::parameter_description(&path, &parameter.name) {
+                for locale in ENGLISH_DISCORD_LOCALES {
+                    parameter
+                        .description_localizations
+                        .insert(locale.to_string(), description.to_string());
+                }
+            }
+        }
+
+        localize_commands_under(&format!("{path} "), &mut command.subcommands);
+    }
+}
+
+/// `/help` に載せる、表示言語でのコマンドの説明
+pub fn command_description(
+    command: &poise::Command<Arc<Data>, anyhow::Error>,
+    locale: Locale,
+) -> Option<&str> {
+    let localized = match locale {
+        Locale::Ja => None,
+        Locale::En => command
+            .description_localizations
+            .get(ENGLISH_DISCORD_LOCALES[0]),
+    };
+    localized
+        .map(String::as_str)
+        .or(command.description.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presentation::command::command_registry::commands;
+
+    fn all_paths(
+        parent: &str,
+        commands: &[poise::Command<Arc<Data>, anyhow::Error>],
+        paths: &mut Vec<(String, Vec<String>)>,
+    ) {
+        for command in commands {
+            let path = format!("{parent}{}", command.name);
+            let parameters = command.parameters.iter().map(|p| p.name.clone()).collect();
+            paths.push((path.clone(), parameters));
+            all_paths(&format!("{path} "), &command.subcommands, paths);
+        }
+    }
+
+    #[test]
+    fn every_command_has_an_english_description() {
+        let mut paths = Vec::new();
+        all_paths("", &commands(), &mut paths);
+
+        for (path, parameters) in paths {
+            assert!(
+                en::command_description(&path).is_some(),
+                "missing English description for `{path}`"
+            );
+            for parameter in parameters {
+                assert!(
+                    en::parameter_description(&path, &parameter).is_some(),
+                    "missing English description for `{path}` parameter `{parameter}`"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn english_descriptions_are_registered_for_english_clients() {
+        let commands = commands();
+
+        let access = commands.iter().find(|c| c.name == "access").unwrap();
+        let allow = access
+            .subcommands
+            .iter()
+            .find(|c| c.name == "allow")
+            .unwrap();
+        assert_eq!(
+            command_description(allow, Locale::En),
+            allow
+                .description_localizations
+                .get("en-GB")
+                .map(String::as_str)
+        );
+        assert_eq!(
+            command_description(allow, Locale::Ja),
+            allow.description.as_deref()
+        );
+        assert!(
+            allow.parameters[0]
+                .description_localizations
+                .contains_key("en-US")
+        );
+    }
+
+    #[test]
+    fn error_messages_do_not_expose_details() {
+        let err = AppError::Store("connection refused at 10.0.0.1:6334".to_string());
+
+        assert_eq!(
+            messages(Locale::Ja).error(&err),
+            "記憶の検索に失敗しました。"
+        );
+        assert_eq!(
+            messages(Locale::En).error(&err),
+            "Failed to search memories."
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use serenity::all::{Cache, ChannelId, GuildId, User};
+
+use crate::application::chat::prompt_envelope::MessageMetadata;
+
+pub fn collect_metadata(
+    cache: &Cache,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    author: &User,
+) -> MessageMetadata {
+    let mut guild_name = "DM".to_string();
+    let mut channel_name = channel_id.to_string();
+    let mut category_name = "None".to_string();
+
+    if let Some(guild_id) = guild_id
+        && let Some(guild) = cache.guild(guild_id)
+    {
+        guild_name = guild.name.clone();
+
+        if let Some(channel) = guild.channels.get(&channel_id) {
+            channel_name = channel.name.clone();
+
+            if let Some(parent_id) = channel.parent_id {
+                category_name = guild
+                    .channels
+                    .get(&parent_id)
+                    .map(|cat| cat.name.clone())
+                    .unwrap_or_else(|| "None".to_string());
+            }
+        }
+    }
+
+    MessageMetadata {
+        guild_name,
+        guild_id: guild_id.map(|id| id.get()).unwrap_or(0),
+        category_name,
+        channel_name,
+        channel_id: channel_id.get(),
+        user_name: author.name.clone(),
+        user_id: author.id.get(),
+    }
+}
@@ -1,3 +1,15 @@
+pub mod access;
+pub mod auto_response;
+pub mod cli;
 pub mod command;
+pub mod direct_message;
 pub mod events;
 pub mod handler;
+pub mod health;
+pub mod http;
+pub mod i18n;
+pub mod metadata;
+pub mod outbound;
+pub mod reply_components;
+pub mod reply_sender;
+pub mod reply_tracker;
@@ -0,0 +1,222 @@
+use serenity::all::{CreateAllowedMentions, CreateAttachment};
+
+use crate::{
+    models::locale::Locale,
+    presentation::i18n::messages,
+    shared::{
+        config::{Attachments, MentionPolicy},
+        discord_utils::{package_reply, split_message},
+    },
+};
+
+/// 送信前の応答テキストと、Discordに渡すメンション許可設定の組
+pub struct OutboundReply {
+    pub content: String,
+    pub allowed_mentions: CreateAllowedMentions,
+}
+
+pub struct OutboundMessage {
+    pub content: String,
+    pub files: Vec<CreateAttachment>,
+}
+
+impl OutboundReply {
+    /// 送信するメッセージ列に変換する。閾値を超える応答はプレビューと添付ファイルの1通にまとめる
+    pub fn messages(&self, attachments: &Attachments, locale: Locale) -> Vec<OutboundMessage> {
+        let over_threshold = attachments.threshold_chars > 0
+            && self.content.chars().count() > attachments.threshold_chars;
+
+        if !over_threshold {
+            return split_message(&self.content)
+                .into_iter()
+                .map(|content| OutboundMessage {
+                    content,
+                    files: Vec::new(),
+                })
+                .collect();
+        }
+
+        let packaged = package_reply(
+            &self.content,
+            attachments.code_block_min_lines,
+            attachments.preview_chars,
+            &messages(locale).attachment_notes(),
+        );
+        vec![OutboundMessage {
+            content: packaged.preview,
+            files: packaged
+                .files
+                .into_iter()
+                .map(|file| CreateAttachment::bytes(file.content.into_bytes(), file.filename))
+                .collect(),
+        }]
+    }
+}
+
+/// モデルの応答を送信用に整える
+///
+/// `conversation_text` はユーザーの発言で、そこに含まれるユーザー・ロールと `author_id` だけが通知対象になる。
+pub fn prepare_reply(
+    reply: &str,
+    policy: &MentionPolicy,
+    author_id: u64,
+    conversation_text: &str,
+) -> OutboundReply {
+    let content = if policy.allow_mass_mentions {
+        reply.to_string()
+    } else {
+        neutralize_mass_mentions(reply)
+    };
+
+    let (mut users, roles) = parse_mentions(conversation_text);
+    users.push(author_id);
+
+    let mut allowed_mentions = CreateAllowedMentions::new()
+        .everyone(policy.allow_mass_mentions)
+        .replied_user(false);
+    if policy.allow_user_mentions {
+        allowed_mentions = allowed_mentions.users(users);
+    }
+    if policy.allow_role_mentions {
+        allowed_mentions = allowed_mentions.roles(roles);
+    }
+
+    OutboundReply {
+        content,
+        allowed_mentions,
+    }
+}
+
+/// `@everyone` / `@here` にゼロ幅スペースを挟み、権限の有無に関わらず一斉通知にならないようにする
+pub fn neutralize_mass_mentions(text: &str) -> String {
+    text.replace("@everyone", "@\u{200B}everyone")
+        .replace("@here", "@\u{200B}here")
+}
+
+/// テキスト中の `<@id>` / `<@!id>` と `<@&id>` を抜き出す
+fn parse_mentions(text: &str) -> (Vec<u64>, Vec<u64>) {
+    let mut users = Vec::new();
+    let mut roles = Vec::new();
+
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        rest = &rest[start + 2 ..];
+
+        let (is_role, body) = match rest.as_bytes().first() {
+            Some(b'&') => (true, &rest[1 ..]),
+            Some(b'!') => (false, &rest[1 ..]),
+            _ => (false, rest),
+        };
+
+        let Some(end) = body.find('>') else {
+            break;
+        };
+        if let Ok(id) = body[.. end].parse::<u64>() {
+            if is_role {
+                roles.push(id);
+            } else {
+                users.push(id);
+            }
+        }
+    }
+
+    (users, roles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(users: bool, roles: bool, mass: bool) -> MentionPolicy {
+        MentionPolicy {
+            allow_user_mentions: users,
+            allow_role_mentions: roles,
+            allow_mass_mentions: mass,
+        }
+    }
+
+    #[test]
+    fn mass_mentions_are_neutralized() {
+        let reply = prepare_reply(
+            "Hey @everyone and @here!",
+            &policy(true, false, false),
+            1,
+            "",
+        );
+        assert_eq!(reply.content, "Hey @\u{200B}everyone and @\u{200B}here!");
+        assert_eq!(
+            reply.allowed_mentions,
+            CreateAllowedMentions::new()
+                .everyone(false)
+                .replied_user(false)
+                .users([1u64])
+        );
+    }
+
+    #[test]
+    fn mass_mentions_kept_when_allowed() {
+        let reply = prepare_reply("@everyone", &policy(true, false, true), 1, "");
+        assert_eq!(reply.content, "@everyone");
+    }
+
+    #[test]
+    fn only_conversation_users_are_allowed() {
+        let reply = prepare_reply(
+            "<@2> <@3> <@&4>",
+            &policy(true, false, false),
+            1,
+            "ask <@!2> about <@&4>",
+        );
+        assert_eq!(
+            reply.allowed_mentions,
+            CreateAllowedMentions::new()
+                .replied_user(false)
+                .users([2u64, 1])
+        );
+    }
+
+    #[test]
+    fn roles_allowed_only_when_policy_permits() {
+        let reply = prepare_reply("", &policy(false, true, false), 1, "<@&4> <@5>");
+        assert_eq!(
+            reply.allowed_mentions,
+            CreateAllowedMentions::new()
+                .replied_user(false)
+                .roles([4u64])
+        );
+    }
+
+    #[test]
+    fn long_reply_becomes_single_message_with_files() {
+        let reply = prepare_reply(&"word ".repeat(1000), &policy(true, false, false), 1, "");
+        let attachments = Attachments {
+            threshold_chars: 4000,
+            ..Default::default()
+        };
+
+        let messages = reply.messages(&attachments, Locale::Ja);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].files.len(), 1);
+        assert_eq!(messages[0].files[0].filename, "answer.md");
+    }
+
+    #[test]
+    fn disabled_threshold_splits_instead() {
+        let reply = prepare_reply(&"word ".repeat(1000), &policy(true, false, false), 1, "");
+        let attachments = Attachments {
+            threshold_chars: 0,
+            ..Default::default()
+        };
+
+        let messages = reply.messages(&attachments, Locale::Ja);
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| m.files.is_empty()));
+    }
+
+    #[test]
+    fn parse_mentions_ignores_malformed_input() {
+        let (users, roles) = parse_mentions("<@abc> <@12 <@&> <@34>");
+        assert_eq!(users, vec![34]);
+        assert!(roles.is_empty());
+    }
+}
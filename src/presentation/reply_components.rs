@@ -0,0 +1,121 @@
+use serenity::all::{ButtonStyle, CreateActionRow, CreateButton};
+
+use crate::{models::locale::Locale, presentation::i18n::messages};
+
+/// ボタンの `custom_id` の接頭辞。他のコンポーネントと区別する
+const CUSTOM_ID_PREFIX: &str = "reply";
+
+/// 応答に付けるボタンの操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyAction {
+    Regenerate,
+    Continue,
+    Delete,
+}
+
+impl ReplyAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReplyAction::Regenerate => "regenerate",
+            ReplyAction::Continue => "continue",
+            ReplyAction::Delete => "delete",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "regenerate" => Some(ReplyAction::Regenerate),
+            "continue" => Some(ReplyAction::Continue),
+            "delete" => Some(ReplyAction::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// 押されたボタンが指すターンと、操作を許可されたユーザー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplyButton {
+    pub action: ReplyAction,
+    pub turn_id: u64,
+    pub user_id: u64,
+}
+
+impl ReplyButton {
+    pub fn custom_id(&self) -> String {
+        format!(
+            "{CUSTOM_ID_PREFIX}:{}:{}:{}",
+            self.action.as_str(),
+            self.turn_id,
+            self.user_id
+        )
+    }
+
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.split(':');
+        if parts.next()? != CUSTOM_ID_PREFIX {
+            return None;
+        }
+        let action = ReplyAction::parse(parts.next()?)?;
+        let turn_id = parts.next()?.parse().ok()?;
+        let user_id = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            action,
+            turn_id,
+            user_id,
+        })
+    }
+}
+
+/// 応答の最後のメッセージに付ける「再生成」「続き」「削除」ボタン
+pub fn reply_buttons(turn_id: u64, user_id: u64, locale: Locale) -> Vec<CreateActionRow> {
+    let messages = messages(locale);
+    let button = |action| ReplyButton {
+        action,
+        turn_id,
+        user_id,
+    };
+
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(button(ReplyAction::Regenerate).custom_id())
+            .label(messages.button_label(ReplyAction::Regenerate))
+            .emoji('🔄')
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(button(ReplyAction::Continue).custom_id())
+            .label(messages.button_label(ReplyAction::Continue))
+            .emoji('▶')
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(button(ReplyAction::Delete).custom_id())
+            .label(messages.button_label(ReplyAction::Delete))
+            .emoji('🗑')
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_id_round_trips() {
+        let button = ReplyButton {
+            action: ReplyAction::Continue,
+            turn_id: 1234567890123456789,
+            user_id: 42,
+        };
+        assert_eq!(button.custom_id(), "reply:continue:1234567890123456789:42");
+        assert_eq!(ReplyButton::parse(&button.custom_id()), Some(button));
+    }
+
+    #[test]
+    fn foreign_custom_ids_are_ignored() {
+        assert_eq!(ReplyButton::parse("other:delete:1:2"), None);
+        assert_eq!(ReplyButton::parse("reply:explode:1:2"), None);
+        assert_eq!(ReplyButton::parse("reply:delete:1"), None);
+        assert_eq!(ReplyButton::parse("reply:delete:1:2:3"), None);
+        assert_eq!(ReplyButton::parse("reply:delete:x:2"), None);
+    }
+}
@@ -0,0 +1,135 @@
+use serenity::all::{
+    ChannelId, CreateActionRow, CreateMessage, EditAttachments, EditMessage, Http, MessageId,
+    MessageReference,
+};
+
+use crate::{
+    models::locale::Locale, presentation::outbound::OutboundReply, shared::config::Attachments,
+};
+
+/// 応答を送信し、送れたメッセージのIDを返す。`components` は最後のメッセージに付ける
+pub async fn send_reply(
+    http: &Http,
+    channel_id: ChannelId,
+    outbound: &OutboundReply,
+    attachments: &Attachments,
+    locale: Locale,
+    reference: Option<MessageReference>,
+    components: Vec<CreateActionRow>,
+) -> Vec<u64> {
+    let messages = outbound.messages(attachments, locale);
+    let last = messages.len().saturating_sub(1);
+
+    let mut sent_ids = Vec::new();
+    for (i, message) in messages.into_iter().enumerate() {
+        let mut builder = CreateMessage::new()
+            .content(message.content)
+            .add_files(message.files)
+            .allowed_mentions(outbound.allowed_mentions.clone());
+        if i == 0
+            && let Some(reference) = reference.clone()
+        {
+            builder = builder.reference_message(reference);
+        }
+        if i == last {
+            builder = builder.components(components.clone());
+        }
+
+        match channel_id.send_message(http, builder).await {
+            Ok(sent) => sent_ids.push(sent.id.get()),
+            Err(e) => {
+                tracing::error!("Error sending message: {:?}", e);
+                break;
+            }
+        }
+    }
+    sent_ids
+}
+
+/// 送信済みの応答を新しい内容で書き換える
+///
+/// 足りない分は追加で送り、余った古いメッセージは削除する。書き換え後のメッセージIDを返す。
+pub async fn edit_reply(
+    http: &Http,
+    channel_id: ChannelId,
+    existing_ids: &[u64],
+    outbound: &OutboundReply,
+    attachments: &Attachments,
+    locale: Locale,
+    components: Vec<CreateActionRow>,
+) -> Vec<u64> {
+    let messages = outbound.messages(attachments, locale);
+    let last = messages.len().saturating_sub(1);
+    let mut existing = existing_ids.iter().copied();
+
+    let mut reply_ids = Vec::new();
+    for (i, message) in messages.into_iter().enumerate() {
+        let components = if i == last {
+            components.clone()
+        } else {
+            Vec::new()
+        };
+
+        let result = match existing.next() {
+            Some(id) => {
+                let attachments = message
+                    .files
+                    .into_iter()
+                    .fold(EditAttachments::new(), |attachments, file| {
+                        attachments.add(file)
+                    });
+                let builder = EditMessage::new()
+                    .content(message.content)
+                    .attachments(attachments)
+                    .allowed_mentions(outbound.allowed_mentions.clone())
+                    .components(components);
+                channel_id
+                    .edit_message(http, MessageId::new(id), builder)
+                    .await
+            }
+            None => {
+                let builder = CreateMessage::new()
+                    .content(message.content)
+                    .add_files(message.files)
+                    .allowed_mentions(outbound.allowed_mentions.clone())
+                    .components(components);
+                channel_id.send_message(http, builder).await
+            }
+        };
+
+        match result {
+            Ok(sent) => reply_ids.push(sent.id.get()),
+            Err(err) => {
+                tracing::error!("Error updating reply: {:?}", err);
+                break;
+            }
+        }
+    }
+
+    // 新しい応答の方が短ければ、余った古いメッセージを消す
+    delete_messages(http, channel_id, existing).await;
+
+    reply_ids
+}
+
+/// メッセージのボタンを外す。続きを送った後の古い最後のメッセージなどに使う
+pub async fn remove_components(http: &Http, channel_id: ChannelId, message_id: u64) {
+    if let Err(err) = channel_id
+        .edit_message(
+            http,
+            MessageId::new(message_id),
+            EditMessage::new().components(Vec::new()),
+        )
+        .await
+    {
+        tracing::warn!(message_id, "Failed to remove reply buttons: {err}");
+    }
+}
+
+pub async fn delete_messages(http: &Http, channel_id: ChannelId, ids: impl Iterator<Item = u64>) {
+    for id in ids {
+        if let Err(err) = channel_id.delete_message(http, MessageId::new(id)).await {
+            tracing::warn!(message_id = id, "Failed to delete stale reply: {err}");
+        }
+    }
+}
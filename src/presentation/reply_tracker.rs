@@ -0,0 +1,138 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// 覚えておく応答の最大件数。古いものから忘れる
+const MAX_TRACKED_REPLIES: usize = 1000;
+
+/// 応答を生成した時の入力と出力。評価と一緒に保存する
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Generation {
+    pub prompt: String,
+    pub memories: Vec<String>,
+    pub response: String,
+}
+
+/// ユーザーの発言に対してボットが送った応答
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedReply {
+    pub channel_id: u64,
+    pub user_id: u64,
+    /// 応答を生成した時点の発言本文。埋め込みの展開など本文以外の更新を無視するために比較する
+    pub source_content: String,
+    pub reply_message_ids: Vec<u64>,
+    pub generation: Generation,
+}
+
+/// 発言のメッセージIDから、それに対する応答を引けるようにする
+pub struct ReplyTracker {
+    capacity: usize,
+    inner: Mutex<TrackerInner>,
+}
+
+#[derive(Default)]
+struct TrackerInner {
+    replies: HashMap<u64, TrackedReply>,
+    order: VecDeque<u64>,
+}
+
+impl Default for ReplyTracker {
+    fn default() -> Self {
+        Self::with_capacity(MAX_TRACKED_REPLIES)
+    }
+}
+
+impl ReplyTracker {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(TrackerInner::default()),
+        }
+    }
+
+    pub fn track(&self, source_message_id: u64, reply: TrackedReply) {
+        let mut inner = self.lock();
+        if inner.replies.insert(source_message_id, reply).is_none() {
+            inner.order.push_back(source_message_id);
+        }
+
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.replies.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn get(&self, source_message_id: u64) -> Option<TrackedReply> {
+        self.lock().replies.get(&source_message_id).cloned()
+    }
+
+    /// 応答のメッセージIDから、元の発言のIDと応答を引く
+    pub fn find_by_reply(&self, reply_message_id: u64) -> Option<(u64, TrackedReply)> {
+        self.lock()
+            .replies
+            .iter()
+            .find(|(_, reply)| reply.reply_message_ids.contains(&reply_message_id))
+            .map(|(source_id, reply)| (*source_id, reply.clone()))
+    }
+
+    pub fn remove(&self, source_message_id: u64) -> Option<TrackedReply> {
+        let mut inner = self.lock();
+        inner.order.retain(|id| *id != source_message_id);
+        inner.replies.remove(&source_message_id)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TrackerInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(ids: &[u64]) -> TrackedReply {
+        TrackedReply {
+            channel_id: 1,
+            user_id: 2,
+            source_content: "hi".to_string(),
+            reply_message_ids: ids.to_vec(),
+            generation: Generation::default(),
+        }
+    }
+
+    #[test]
+    fn retracking_replaces_reply() {
+        let tracker = ReplyTracker::default();
+        tracker.track(10, reply(&[100]));
+        tracker.track(10, reply(&[100, 101]));
+        assert_eq!(tracker.get(10), Some(reply(&[100, 101])));
+    }
+
+    #[test]
+    fn reply_is_found_by_any_of_its_messages() {
+        let tracker = ReplyTracker::default();
+        tracker.track(10, reply(&[100, 101]));
+        tracker.track(11, reply(&[102]));
+
+        assert_eq!(tracker.find_by_reply(101).map(|(id, _)| id), Some(10));
+        assert_eq!(tracker.find_by_reply(102).map(|(id, _)| id), Some(11));
+        assert!(tracker.find_by_reply(10).is_none());
+    }
+
+    #[test]
+    fn oldest_reply_is_forgotten() {
+        let tracker = ReplyTracker::with_capacity(2);
+        tracker.track(10, reply(&[100]));
+        tracker.track(11, reply(&[101]));
+        tracker.track(10, reply(&[102]));
+        tracker.track(12, reply(&[103]));
+
+        assert!(tracker.get(10).is_none());
+        assert!(tracker.get(11).is_some());
+        assert!(tracker.get(12).is_some());
+    }
+}
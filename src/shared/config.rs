@@ -1,11 +1,56 @@
+use std::net::SocketAddr;
+
 use anyhow::Result;
-use config::{Config as ConfigBuilder, ConfigError, Environment, File};
-use serde::Deserialize;
+use config::{ConfigBuilder, ConfigError, Environment, File, builder::DefaultState};
+use serde::{Deserialize, Deserializer};
+use tracing_subscriber::EnvFilter;
+
+use crate::shared::{
+    discord_utils::DISCORD_MAX_LENGTH,
+    secret::{Secret, read_secret_file},
+};
+
+/// `<キー>_file` にファイルのパスを指定すると、値をそのファイルから読み込む項目
+const SECRET_KEYS: [&str; 4] = ["nlp_token", "embed_token", "discord_token", "chat_api_keys"];
+
+/// Qdrantが扱えるベクトルの最大次元数
+const MAX_EMBEDDING_DIMENSION: u64 = 65536;
 
 fn default_log_level() -> String {
     "info".to_string()
 }
 
+/// `.env` で `guild_id=` のように値を空にした項目を未設定として扱う
+fn empty_as_none<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(u64),
+        Text(String),
+    }
+
+    match Option::<Raw>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Raw::Number(n)) => Ok(Some(n)),
+        Some(Raw::Text(text)) if text.trim().is_empty() => Ok(None),
+        Some(Raw::Text(text)) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+fn empty_string_as_none<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|s| !s.trim().is_empty()))
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct NLP {
     pub api_url: String,
@@ -21,38 +66,782 @@ pub struct Embedding {
 }
 
 #[derive(Debug, Clone, Deserialize)]
-pub struct Config {
-    pub nlp_token: String,
-    pub embed_token: String,
-    pub discord_token: String,
+pub struct PromotionRetry {
+    pub queue_path: String,
+    pub max_attempts: u32,
+    pub base_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    pub interval_secs: u64,
+}
+
+impl Default for PromotionRetry {
+    fn default() -> Self {
+        Self {
+            queue_path: "data/promotion_queue.json".to_string(),
+            max_attempts: 8,
+            base_backoff_secs: 30,
+            max_backoff_secs: 60 * 60,
+            interval_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitBreaker {
+    pub failure_threshold: u32,
+    pub open_secs: u64,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            open_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MentionPolicy {
+    /// 会話に登場したユーザーへのメンションを許可する
+    pub allow_user_mentions: bool,
+    /// 会話に登場したロールへのメンションを許可する
+    pub allow_role_mentions: bool,
+    /// `@everyone` / `@here` を許可する
+    pub allow_mass_mentions: bool,
+}
+
+impl Default for MentionPolicy {
+    fn default() -> Self {
+        Self {
+            allow_user_mentions: true,
+            allow_role_mentions: false,
+            allow_mass_mentions: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildMentionPolicy {
     pub guild_id: u64,
+    pub policy: MentionPolicy,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Mentions {
+    pub default: MentionPolicy,
+    pub guilds: Vec<GuildMentionPolicy>,
+}
+
+impl Mentions {
+    pub fn policy_for(&self, guild_id: Option<u64>) -> &MentionPolicy {
+        guild_id
+            .and_then(|id| self.guilds.iter().find(|g| g.guild_id == id))
+            .map(|g| &g.policy)
+            .unwrap_or(&self.default)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Attachments {
+    /// この文字数を超える応答はファイルとして送る。0で無効
+    pub threshold_chars: usize,
+    /// この行数以上のコードブロックは個別のファイルに切り出す
+    pub code_block_min_lines: usize,
+    pub preview_chars: usize,
+}
+
+impl Default for Attachments {
+    fn default() -> Self {
+        Self {
+            threshold_chars: 4000,
+            code_block_min_lines: 30,
+            preview_chars: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectMessageMode {
+    Disabled,
+    /// `guild_id` / `guilds.guild_ids` のサーバーのメンバーのみ。未設定ならボットが参加している全サーバーのメンバー
+    #[default]
+    GuildMembers,
+    Everyone,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DirectMessages {
+    pub mode: DirectMessageMode,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GuildSettingsStorage {
+    pub path: String,
+}
+
+impl Default for GuildSettingsStorage {
+    fn default() -> Self {
+        Self {
+            path: "data/guild_settings.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UserSettingsStorage {
+    pub path: String,
+}
+
+impl Default for UserSettingsStorage {
+    fn default() -> Self {
+        Self {
+            path: "data/user_settings.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FeedbackStorage {
+    pub path: String,
+}
+
+impl Default for FeedbackStorage {
+    fn default() -> Self {
+        Self {
+            path: "data/feedback.jsonl".to_string(),
+        }
+    }
+}
+
+/// メンション無しで応答するチャンネルでの連投対策
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoResponse {
+    /// 同じチャンネルで次に自動応答するまでの秒数
+    pub channel_cooldown_secs: u64,
+    /// 同じユーザーに次に自動応答するまでの秒数
+    pub user_cooldown_secs: u64,
+}
+
+impl Default for AutoResponse {
+    fn default() -> Self {
+        Self {
+            channel_cooldown_secs: 3,
+            user_cooldown_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandRegistration {
+    /// `guild_id` と `guilds.guild_ids` のサーバーにだけ登録する。即座に反映される
+    #[default]
+    Guilds,
+    /// 全サーバーに登録する。反映に時間がかかることがある
+    Global,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Guilds {
+    pub command_registration: CommandRegistration,
+    /// `guild_id` に加えて対象とするサーバー
+    pub guild_ids: Vec<u64>,
+    /// サーバーから退出させられた時に、そのサーバーで得た中期・長期記憶も削除する
+    pub purge_memory_on_leave: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HealthServer {
+    /// `/healthz` と `/readyz` を公開する
+    pub enabled: bool,
+    /// コンテナで使う場合は `0.0.0.0:8080` など外から届くアドレスにする
+    pub address: String,
+    /// 依存先の確認結果を使い回す秒数。埋め込みAPIの確認は課金されるため
+    pub cache_secs: u64,
+}
+
+impl Default for HealthServer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:8080".to_string(),
+            cache_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsServer {
+    /// Prometheus形式の `/metrics` を公開する
+    pub enabled: bool,
+    pub address: String,
+}
+
+impl Default for MetricsServer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:9090".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Shutdown {
+    /// SIGTERMを受け取ってから、処理中の応答を待って終了するまでの上限
+    pub timeout_secs: u64,
+    /// 終了時に短期記憶を書き出し、次の起動時に読み戻すファイル
+    pub short_term_path: String,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            short_term_path: "data/short_term.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ChatApi {
+    /// OpenAI互換の `/v1/chat/completions` を公開する。APIキーは `chat_api_keys` で設定する
+    pub enabled: bool,
+    pub address: String,
+    /// `model` として返す名前
+    pub model_name: String,
+}
+
+impl Default for ChatApi {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:8000".to_string(),
+            model_name: "nekoai".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// 1行1イベントのJSON。スパンのフィールド（チャンネル・ユーザー・サーバー）も含める
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Logging {
+    pub format: LogFormat,
+    /// OTLP/HTTPでトレースを送る先（例: `http://localhost:4318/v1/traces`）。空なら送らない
+    #[serde(deserialize_with = "empty_string_as_none")]
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            otlp_endpoint: None,
+            service_name: "nekoai".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// 未設定でも読み込みは通し、`validate` でまとめて報告する
+    #[serde(default)]
+    pub nlp_token: Secret,
+    #[serde(default)]
+    pub embed_token: Secret,
+    #[serde(default)]
+    pub discord_token: Secret,
+    /// `<APIキー>=<ユーザーID>` をカンマ区切りで並べたもの。`chat_api` で使う
+    #[serde(default)]
+    pub chat_api_keys: Secret,
+    /// 主に使うサーバー。複数のサーバーで使う場合は `guilds` で設定する
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub guild_id: Option<u64>,
     pub qdrant_url: String,
 
     #[serde(default = "default_log_level")]
     pub log_level: String,
 
+    #[serde(default)]
+    pub logging: Logging,
+
     pub nlp: NLP,
     pub embedding: Embedding,
+
+    #[serde(default)]
+    pub promotion_retry: PromotionRetry,
+
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreaker,
+
+    #[serde(default)]
+    pub mentions: Mentions,
+
+    #[serde(default)]
+    pub attachments: Attachments,
+
+    #[serde(default)]
+    pub direct_messages: DirectMessages,
+
+    #[serde(default)]
+    pub guild_settings: GuildSettingsStorage,
+
+    #[serde(default)]
+    pub user_settings: UserSettingsStorage,
+
+    #[serde(default)]
+    pub auto_response: AutoResponse,
+
+    #[serde(default)]
+    pub feedback: FeedbackStorage,
+
+    #[serde(default)]
+    pub guilds: Guilds,
+
+    #[serde(default)]
+    pub health_server: HealthServer,
+
+    #[serde(default)]
+    pub metrics_server: MetricsServer,
+
+    #[serde(default)]
+    pub chat_api: ChatApi,
+
+    #[serde(default)]
+    pub shutdown: Shutdown,
 }
 
 impl Config {
+    /// `guild_id` と `guilds.guild_ids` を合わせた、設定で指定されたサーバー
+    pub fn guild_ids(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .guild_id
+            .into_iter()
+            .chain(self.guilds.guild_ids.iter().copied())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// `.env` と `config/settings.toml` と環境変数から読み込み、検証する。
+    /// 環境変数だけで設定する場合、`.env` は無くてもよい
     pub fn load() -> Result<Self, ConfigError> {
         dotenvy::dotenv().ok();
 
-        let config = ConfigBuilder::builder()
+        let config = config::Config::builder()
             .add_source(
                 File::with_name(".env")
                     .format(config::FileFormat::Ini)
-                    .required(true),
+                    .required(false),
             )
             .add_source(
                 File::with_name("config/settings.toml")
                     .format(config::FileFormat::Toml)
                     .required(true),
             )
-            .add_source(Environment::default().separator("__"))
-            .build()?;
+            .add_source(Environment::default().separator("__"));
+
+        let config = with_secret_files(config, |key| std::env::var(key).ok())?.build()?;
+
+        let config: Self = config.try_deserialize()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 起動してから失敗するような値を、まとめて検出する
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(ConfigError::Message(format!(
+            "Invalid configuration:\n  - {}",
+            problems.join("\n  - ")
+        )))
+    }
+
+    /// `chat_api_keys` のAPIキーと、そのキーで会話するユーザーのID。
+    /// エラーには何番目の項目かだけを含め、キーは含めない
+    pub fn chat_api_keys(&self) -> Result<Vec<(Secret, u64)>, ConfigError> {
+        self.chat_api_keys
+            .expose()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .enumerate()
+            .map(|(index, entry)| {
+                entry
+                    .rsplit_once('=')
+                    .and_then(|(key, user_id)| {
+                        let key = key.trim();
+                        let user_id = user_id.trim().parse().ok()?;
+                        (!key.is_empty()).then(|| (Secret::new(key), user_id))
+                    })
+                    .ok_or_else(|| {
+                        ConfigError::Message(format!(
+                            "chat_api_keys entry {} is not in <key>=<user_id> form",
+                            index + 1
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Discordに接続する場合だけ必要な項目。CLIのサブコマンドはトークン無しで使える
+    pub fn validate_discord(&self) -> Result<(), ConfigError> {
+        if self.discord_token.is_empty() {
+            return Err(ConfigError::Message(empty_secret_message("discord_token")));
+        }
+        Ok(())
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, message: String| {
+            if !ok {
+                problems.push(message);
+            }
+        };
+
+        for (key, secret) in [
+            ("nlp_token", &self.nlp_token),
+            ("embed_token", &self.embed_token),
+        ] {
+            check(!secret.is_empty(), empty_secret_message(key));
+        }
+
+        for (key, url) in [
+            ("qdrant_url", &self.qdrant_url),
+            ("nlp.api_url", &self.nlp.api_url),
+            ("embedding.api_url", &self.embedding.api_url),
+        ] {
+            check(
+                is_http_url(url),
+                format!("{key} is not a valid http(s) URL: {url:?}"),
+            );
+        }
+        if let Some(endpoint) = &self.logging.otlp_endpoint {
+            check(
+                is_http_url(endpoint),
+                format!("logging.otlp_endpoint is not a valid http(s) URL: {endpoint:?}"),
+            );
+        }
+        check(
+            EnvFilter::try_new(&self.log_level).is_ok(),
+            format!("log_level is not a valid filter: {:?}", self.log_level),
+        );
+        check(
+            !self.logging.service_name.trim().is_empty(),
+            "logging.service_name is empty".to_string(),
+        );
+
+        check(
+            !self.nlp.model_name.trim().is_empty(),
+            "nlp.model_name is empty".to_string(),
+        );
+        check(
+            !self.embedding.model_name.trim().is_empty(),
+            "embedding.model_name is empty".to_string(),
+        );
+        check(
+            self.nlp.max_short_term_messages > 0,
+            "nlp.max_short_term_messages must be at least 1".to_string(),
+        );
+        check(
+            (1 ..= MAX_EMBEDDING_DIMENSION).contains(&self.embedding.dimension),
+            format!(
+                "embedding.dimension must be between 1 and {MAX_EMBEDDING_DIMENSION}, got {}",
+                self.embedding.dimension
+            ),
+        );
+
+        let retry = &self.promotion_retry;
+        check(
+            retry.max_attempts > 0,
+            "promotion_retry.max_attempts must be at least 1".to_string(),
+        );
+        check(
+            retry.base_backoff_secs <= retry.max_backoff_secs,
+            format!(
+                "promotion_retry.base_backoff_secs ({}) must not exceed max_backoff_secs ({})",
+                retry.base_backoff_secs, retry.max_backoff_secs
+            ),
+        );
+        check(
+            self.shutdown.timeout_secs > 0,
+            "shutdown.timeout_secs must be at least 1".to_string(),
+        );
+        check(
+            self.circuit_breaker.failure_threshold > 0,
+            "circuit_breaker.failure_threshold must be at least 1".to_string(),
+        );
+        check(
+            self.attachments.threshold_chars == 0
+                || self.attachments.preview_chars <= self.attachments.threshold_chars,
+            "attachments.preview_chars must not exceed threshold_chars".to_string(),
+        );
+        check(
+            self.attachments.preview_chars <= DISCORD_MAX_LENGTH,
+            format!(
+                "attachments.preview_chars must not exceed Discord's {DISCORD_MAX_LENGTH}-character message limit"
+            ),
+        );
+
+        let servers = [
+            (
+                "health_server",
+                self.health_server.enabled,
+                &self.health_server.address,
+            ),
+            (
+                "metrics_server",
+                self.metrics_server.enabled,
+                &self.metrics_server.address,
+            ),
+            ("chat_api", self.chat_api.enabled, &self.chat_api.address),
+        ];
+        for (key, enabled, address) in servers {
+            check(
+                !enabled || address.parse::<SocketAddr>().is_ok(),
+                format!("{key}.address is not a valid socket address: {address:?}"),
+            );
+        }
+        for (i, (key, enabled, address)) in servers.iter().enumerate() {
+            for (other, other_enabled, other_address) in &servers[i + 1 ..] {
+                check(
+                    !(*enabled && *other_enabled && address == other_address),
+                    format!("{key} and {other} cannot share the same address"),
+                );
+            }
+        }
+        if self.chat_api.enabled {
+            match self.chat_api_keys() {
+                Ok(keys) => check(
+                    !keys.is_empty(),
+                    "chat_api is enabled but chat_api_keys is empty".to_string(),
+                ),
+                Err(err) => check(false, err.to_string()),
+            }
+        }
+
+        problems
+    }
+}
+
+fn empty_secret_message(key: &str) -> String {
+    format!("{key} is empty (set it in .env, the environment or {key}_file)")
+}
+
+fn is_http_url(value: &str) -> bool {
+    reqwest::Url::parse(value)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
+
+/// `NLP_TOKEN_FILE` などが指定されていれば、そのファイルの中身で値を上書きする。
+/// エラーにはパスだけを含め、値は含めない
+fn with_secret_files(
+    mut builder: ConfigBuilder<DefaultState>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+    for key in SECRET_KEYS {
+        let file_key = format!("{key}_file");
+        let Some(path) = lookup(&file_key.to_uppercase())
+            .or_else(|| lookup(&file_key))
+            .filter(|path| !path.trim().is_empty())
+        else {
+            continue;
+        };
+
+        let secret = read_secret_file(path.trim()).map_err(|err| {
+            ConfigError::Message(format!(
+                "Failed to read {file_key} ({}): {err}",
+                path.trim()
+            ))
+        })?;
+        builder = builder.set_override(key, secret.expose())?;
+    }
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct GuildIdOnly {
+        #[serde(default, deserialize_with = "empty_as_none")]
+        guild_id: Option<u64>,
+    }
+
+    fn parse(json: &str) -> Option<u64> {
+        serde_json::from_str::<GuildIdOnly>(json).unwrap().guild_id
+    }
+
+    #[test]
+    fn guild_id_accepts_empty_and_numeric_strings() {
+        assert_eq!(parse(r#"{}"#), None);
+        assert_eq!(parse(r#"{"guild_id": ""}"#), None);
+        assert_eq!(parse(r#"{"guild_id": "123"}"#), Some(123));
+        assert_eq!(parse(r#"{"guild_id": 123}"#), Some(123));
+        assert!(serde_json::from_str::<GuildIdOnly>(r#"{"guild_id": "abc"}"#).is_err());
+    }
+
+    #[test]
+    fn empty_otlp_endpoint_disables_export() {
+        let logging: Logging =
+            serde_json::from_str(r#"{"format": "json", "otlp_endpoint": ""}"#).unwrap();
+        assert_eq!(logging.format, LogFormat::Json);
+        assert_eq!(logging.otlp_endpoint, None);
+        assert_eq!(logging.service_name, "nekoai");
+    }
+
+    #[test]
+    fn secret_file_overrides_token_without_leaking_it() {
+        let path = crate::test_support::temp_path();
+        std::fs::write(&path, "discord-token-from-file\n").unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+
+        let builder = config::Config::builder()
+            .set_default("discord_token", "from-env")
+            .unwrap();
+        let config = with_secret_files(builder, |key| {
+            (key == "DISCORD_TOKEN_FILE").then(|| path_str.clone())
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+
+        #[derive(Debug, Deserialize)]
+        struct Tokens {
+            discord_token: Secret,
+        }
+        let tokens: Tokens = config.try_deserialize().unwrap();
+        assert_eq!(tokens.discord_token.expose(), "discord-token-from-file");
+        assert!(!format!("{tokens:#?}").contains("discord-token-from-file"));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn missing_secret_file_error_does_not_contain_a_value() {
+        // 先に読めた秘密と、環境変数などで設定済みの秘密のどちらもエラーに含まれないこと
+        let path = crate::test_support::temp_path();
+        std::fs::write(&path, "nlp-token-from-file\n").unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+        let builder = config::Config::builder()
+            .set_default("embed_token", "embed-token-from-env")
+            .unwrap();
+
+        let err = with_secret_files(builder, |key| match key {
+            "nlp_token_file" => Some(path_str.clone()),
+            "discord_token_file" => Some("/nonexistent/neko_ai_secret".to_string()),
+            _ => None,
+        })
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("discord_token_file"));
+        assert!(err.contains("/nonexistent/neko_ai_secret"));
+        assert!(!err.contains("nlp-token-from-file"));
+        assert!(!err.contains("embed-token-from-env"));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    fn sample_config() -> Config {
+        serde_json::from_value(serde_json::json!({
+            "nlp_token": "nlp-secret",
+            "embed_token": "embed-secret",
+            "discord_token": "discord-secret",
+            "qdrant_url": "http://localhost:6334",
+            "log_level": "info",
+            "nlp": {
+                "api_url": "https://openrouter.ai/api/v1",
+                "model_name": "model",
+                "max_short_term_messages": 20
+            },
+            "embedding": {
+                "api_url": "https://openrouter.ai/api/v1",
+                "model_name": "embedding",
+                "dimension": 1536
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sample_config_is_valid() {
+        sample_config().validate().unwrap();
+    }
+
+    #[test]
+    fn validation_reports_every_problem_without_secrets() {
+        let mut config = sample_config();
+        config.nlp_token = Secret::new("  ");
+        config.qdrant_url = "localhost:6334".to_string();
+        config.embedding.dimension = 0;
+        config.metrics_server.enabled = true;
+        config.metrics_server.address = "not an address".to_string();
+        config.attachments.preview_chars = 2001;
+
+        let message = config.validate().unwrap_err().to_string();
+
+        assert!(message.contains("nlp_token is empty"));
+        assert!(message.contains("qdrant_url"));
+        assert!(message.contains("embedding.dimension"));
+        assert!(message.contains("metrics_server.address"));
+        assert!(message.contains("attachments.preview_chars must not exceed Discord's"));
+        assert!(!message.contains("embed-secret"));
+        assert!(!message.contains("discord-secret"));
+    }
+
+    #[test]
+    fn discord_token_is_only_required_for_the_bot() {
+        let mut config = sample_config();
+        config.discord_token = Secret::default();
+
+        config.validate().unwrap();
+        assert!(config.validate_discord().is_err());
+    }
+
+    #[test]
+    fn chat_api_keys_are_parsed_without_leaking_them() {
+        let mut config = sample_config();
+        config.chat_api_keys = Secret::new("key-a=1, key-b = 2,");
+
+        let keys = config.chat_api_keys().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], (Secret::new("key-a"), 1));
+        assert_eq!(keys[1], (Secret::new("key-b"), 2));
 
-        config.try_deserialize()
+        config.chat_api_keys = Secret::new("key-a=1,secret-without-user");
+        let message = config.chat_api_keys().unwrap_err().to_string();
+        assert!(message.contains("entry 2"));
+        assert!(!message.contains("secret-without-user"));
     }
 }
@@ -1,32 +1,278 @@
-const DISCORD_MAX_LENGTH: usize = 2000;
+/// Discordのメッセージ上限。DiscordはUTF-8のバイト数ではなく文字（コードポイント）数で数える
+pub const DISCORD_MAX_LENGTH: usize = 2000;
 
-pub fn split_message(text: &str) -> Vec<&str> {
-    if text.len() <= DISCORD_MAX_LENGTH {
-        return vec![text];
-    }
+const CODE_FENCE: &str = "```";
+const SENTENCE_ENDS: [&str; 6] = ["。", "！", "？", ". ", "! ", "? "];
+
+pub fn split_message(text: &str) -> Vec<String> {
+    split_message_with_limit(text, DISCORD_MAX_LENGTH)
+}
 
+/// `limit` 文字以内のチャンクに分割する
+///
+/// 段落 → 行 → 文 → 空白の順に区切り位置を探し、コードブロックの途中で分割した場合は
+/// そのチャンクでブロックを閉じ、次のチャンクで同じ言語指定のまま開き直す。
+fn split_message_with_limit(text: &str, limit: usize) -> Vec<String> {
     let mut chunks = Vec::new();
     let mut remaining = text;
+    let mut open_fence: Option<String> = None;
 
-    while !remaining.is_empty() {
-        if remaining.len() <= DISCORD_MAX_LENGTH {
-            chunks.push(remaining);
+    loop {
+        let prefix = open_fence
+            .as_ref()
+            .map(|fence| format!("{fence}\n"))
+            .unwrap_or_default();
+        let prefix_len = char_len(&prefix);
+
+        if prefix_len + char_len(remaining) <= limit {
+            chunks.push(format!("{prefix}{remaining}"));
             break;
         }
 
-        let split_at = remaining[.. DISCORD_MAX_LENGTH]
-            .rfind('\n')
-            .map(|pos| pos + 1)
-            .unwrap_or(DISCORD_MAX_LENGTH);
+        let budget = limit.saturating_sub(prefix_len).max(1);
+        let (mut split_at, mut fence_after) = next_split(remaining, budget, &open_fence);
+
+        // コードブロックの途中で切れる場合は、閉じフェンス（"\n```"）の分を空けて区切り直す
+        if fence_after.is_some()
+            && prefix_len + char_len(&remaining[.. split_at]) + CODE_FENCE.len() + 1 > limit
+        {
+            let budget = limit
+                .saturating_sub(prefix_len + CODE_FENCE.len() + 1)
+                .max(1);
+            (split_at, fence_after) = next_split(remaining, budget, &open_fence);
+        }
 
-        let (chunk, rest) = remaining.split_at(split_at);
+        let (body, rest) = remaining.split_at(split_at);
+        let mut chunk = format!("{prefix}{body}");
+        if fence_after.is_some() {
+            if !chunk.ends_with('\n') {
+                chunk.push('\n');
+            }
+            chunk.push_str(CODE_FENCE);
+        }
         chunks.push(chunk);
+
+        open_fence = fence_after;
         remaining = rest;
     }
 
     chunks
 }
 
+fn next_split(
+    remaining: &str,
+    budget: usize,
+    open_fence: &Option<String>,
+) -> (usize, Option<String>) {
+    let window_end = byte_index_of_char(remaining, budget);
+    let split_at = find_split_point(&remaining[.. window_end]);
+    let fence_after = fence_state_after(open_fence.clone(), &remaining[.. split_at]);
+    (split_at, fence_after)
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// 先頭から `n` 文字目のバイト位置。文字数が足りなければ末尾を返す
+fn byte_index_of_char(text: &str, n: usize) -> usize {
+    text.char_indices()
+        .nth(n)
+        .map(|(idx, _)| idx)
+        .unwrap_or(text.len())
+}
+
+/// `window` の中で最も自然な区切り位置（バイト位置）を返す
+///
+/// 後半に見つかる区切りを優先し、それが無ければ位置を問わず行・空白で区切る。最後の手段として末尾で切る。
+fn find_split_point(window: &str) -> usize {
+    let half = window.len() / 2;
+
+    let paragraph = window.rfind("\n\n").map(|pos| pos + 2);
+    let line = window.rfind('\n').map(|pos| pos + 1);
+    let sentence = SENTENCE_ENDS
+        .iter()
+        .filter_map(|end| window.rfind(end).map(|pos| pos + end.len()))
+        .max();
+    let whitespace = window
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(pos, c)| pos + c.len_utf8());
+
+    [paragraph, line, sentence, whitespace]
+        .into_iter()
+        .flatten()
+        .find(|&pos| pos > half)
+        .or(line.filter(|&pos| pos > 0))
+        .or(whitespace.filter(|&pos| pos > 0))
+        .unwrap_or(window.len())
+}
+
+/// `body` を読み終えた時点で開いているコードブロックの開始行（"```rust" など）を返す
+fn fence_state_after(mut open_fence: Option<String>, body: &str) -> Option<String> {
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if !trimmed.starts_with(CODE_FENCE) {
+            continue;
+        }
+
+        if open_fence.is_some() {
+            open_fence = None;
+        } else if trimmed.len() > CODE_FENCE.len()
+            && trimmed[CODE_FENCE.len() ..].contains(CODE_FENCE)
+        {
+            // ```inline``` のように1行で閉じているものは無視する
+        } else {
+            open_fence = Some(trimmed.to_string());
+        }
+    }
+    open_fence
+}
+
+/// Discordが1メッセージに添付できるファイル数の上限
+const DISCORD_MAX_ATTACHMENTS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplyFile {
+    pub filename: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackagedReply {
+    pub preview: String,
+    pub files: Vec<ReplyFile>,
+}
+
+/// 応答をファイルに切り出した時に本文へ差し込む、表示言語に合わせた文言
+#[derive(Debug, Clone, Copy)]
+pub struct AttachmentNotes {
+    /// コードブロックを置いていた場所に残す参照。引数はファイル名
+    pub code_attached: fn(&str) -> String,
+    /// プレビューの末尾に付ける案内
+    pub full_text_attached: &'static str,
+}
+
+/// 長い応答を、冒頭のプレビューと添付ファイル群にまとめる
+///
+/// 全文は `answer.md` に入れ、`code_block_min_lines` 行以上のコードブロックは言語に応じた拡張子の
+/// ファイルへ切り出して `answer.md` 側には参照だけを残す。
+pub fn package_reply(
+    text: &str,
+    code_block_min_lines: usize,
+    preview_chars: usize,
+    notes: &AttachmentNotes,
+) -> PackagedReply {
+    let mut answer = String::new();
+    let mut code_files = Vec::new();
+    let mut block: Option<(String, Vec<&str>)> = None;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        match block.as_mut() {
+            None if trimmed.starts_with(CODE_FENCE) && fence_state_after(None, line).is_some() => {
+                block = Some((trimmed[CODE_FENCE.len() ..].trim().to_string(), vec![line]));
+            }
+            None => answer.push_str(line),
+            Some((_, lines)) => {
+                lines.push(line);
+                if trimmed.starts_with(CODE_FENCE) {
+                    let (language, lines) = block.take().unwrap_or_default();
+                    let code_lines = lines.len().saturating_sub(2);
+                    // 添付上限から answer.md の1枠を除いた数まで切り出す
+                    if code_lines >= code_block_min_lines
+                        && code_files.len() < DISCORD_MAX_ATTACHMENTS - 1
+                    {
+                        let filename = format!(
+                            "code_{}.{}",
+                            code_files.len() + 1,
+                            extension_for_language(&language)
+                        );
+                        answer.push_str(&(notes.code_attached)(&filename));
+                        answer.push('\n');
+                        code_files.push(ReplyFile {
+                            filename,
+                            content: lines[1 .. lines.len() - 1].concat(),
+                        });
+                    } else {
+                        answer.push_str(&lines.concat());
+                    }
+                }
+            }
+        }
+    }
+    // 閉じられていないコードブロックはそのまま残す
+    if let Some((_, lines)) = block {
+        answer.push_str(&lines.concat());
+    }
+
+    let mut files = vec![ReplyFile {
+        filename: "answer.md".to_string(),
+        content: answer,
+    }];
+    files.extend(code_files);
+
+    PackagedReply {
+        preview: build_preview(text, preview_chars, notes.full_text_attached),
+        files,
+    }
+}
+
+/// 最初のコードブロックより前の本文から、区切りの良い位置までを抜き出す
+fn build_preview(text: &str, preview_chars: usize, full_text_attached: &str) -> String {
+    let prose = text
+        .find(CODE_FENCE)
+        .map(|pos| &text[.. pos])
+        .unwrap_or(text)
+        .trim();
+
+    let mut preview = if char_len(prose) <= preview_chars {
+        prose.to_string()
+    } else {
+        let window = &prose[.. byte_index_of_char(prose, preview_chars)];
+        format!("{}…", window[.. find_split_point(window)].trim_end())
+    };
+
+    if !preview.is_empty() {
+        preview.push_str("\n\n");
+    }
+    preview.push_str(full_text_attached);
+    preview
+}
+
+fn extension_for_language(language: &str) -> &'static str {
+    match language.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => "rs",
+        "python" | "py" => "py",
+        "javascript" | "js" => "js",
+        "typescript" | "ts" => "ts",
+        "jsx" => "jsx",
+        "tsx" => "tsx",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yml",
+        "bash" | "sh" | "shell" | "zsh" => "sh",
+        "c" => "c",
+        "cpp" | "c++" | "cc" => "cpp",
+        "csharp" | "cs" | "c#" => "cs",
+        "go" | "golang" => "go",
+        "java" => "java",
+        "kotlin" | "kt" => "kt",
+        "swift" => "swift",
+        "ruby" | "rb" => "rb",
+        "php" => "php",
+        "lua" => "lua",
+        "html" => "html",
+        "css" => "css",
+        "sql" => "sql",
+        "diff" => "diff",
+        "markdown" | "md" => "md",
+        "xml" => "xml",
+        _ => "txt",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,4 +318,109 @@ mod tests {
         assert_eq!(chunks[1].len(), 2000);
         assert_eq!(chunks[2].len(), 500);
     }
+
+    #[test]
+    fn multibyte_text_splits_on_char_boundaries() {
+        let msg = "猫".repeat(4500);
+        let chunks = split_message(&msg);
+        assert!(chunks.iter().all(|c| char_len(c) <= DISCORD_MAX_LENGTH));
+        assert_eq!(chunks.concat(), msg);
+    }
+
+    #[test]
+    fn japanese_text_prefers_sentence_boundary() {
+        let sentence = format!("{}。", "あ".repeat(99));
+        let msg = sentence.repeat(30);
+        let chunks = split_message(&msg);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.ends_with('。')));
+        assert_eq!(chunks.concat(), msg);
+    }
+
+    #[test]
+    fn prefers_paragraph_boundary() {
+        let msg = format!("{}\n\nbbb\nccc {}", "a".repeat(10), "d".repeat(20));
+        let chunks = split_message_with_limit(&msg, 22);
+        assert_eq!(chunks[0], format!("{}\n\n", "a".repeat(10)));
+    }
+
+    #[test]
+    fn code_fence_is_closed_and_reopened_with_language() {
+        let code: String = (0 .. 300).map(|i| format!("let x{i} = {i};\n")).collect();
+        let msg = format!("Here you go:\n```rust\n{code}```\nDone.");
+        let chunks = split_message(&msg);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(char_len(chunk) <= DISCORD_MAX_LENGTH);
+            assert_eq!(chunk.matches(CODE_FENCE).count() % 2, 0);
+        }
+        for chunk in &chunks[1 ..] {
+            assert!(chunk.starts_with("```rust\n"));
+        }
+        assert!(chunks.last().unwrap().ends_with("```\nDone."));
+    }
+
+    #[test]
+    fn inline_fence_does_not_open_block() {
+        assert_eq!(fence_state_after(None, "```inline```\n"), None);
+        assert_eq!(
+            fence_state_after(None, "```py\nprint()\n"),
+            Some("```py".to_string())
+        );
+        assert_eq!(
+            fence_state_after(Some("```py".to_string()), "x\n```\n"),
+            None
+        );
+    }
+
+    const NOTES: AttachmentNotes = AttachmentNotes {
+        code_attached: |filename| format!("(see `{filename}`)"),
+        full_text_attached: "📎",
+    };
+
+    fn code_block(language: &str, lines: usize) -> String {
+        let body: String = (0 .. lines).map(|i| format!("line {i}\n")).collect();
+        format!("```{language}\n{body}```\n")
+    }
+
+    #[test]
+    fn package_reply_extracts_large_code_blocks() {
+        let msg = format!(
+            "Intro paragraph.\n\n{}Small:\n{}Bye.",
+            code_block("rust", 40),
+            code_block("py", 3)
+        );
+        let packaged = package_reply(&msg, 30, 300, &NOTES);
+
+        assert_eq!(packaged.files.len(), 2);
+        assert_eq!(packaged.files[0].filename, "answer.md");
+        assert_eq!(packaged.files[1].filename, "code_1.rs");
+        assert!(packaged.files[1].content.starts_with("line 0\n"));
+        assert!(!packaged.files[1].content.contains(CODE_FENCE));
+
+        let answer = &packaged.files[0].content;
+        assert!(answer.contains("`code_1.rs`"));
+        assert!(answer.contains(&code_block("py", 3)));
+        assert!(answer.ends_with("Bye."));
+    }
+
+    #[test]
+    fn package_reply_preview_stops_before_code() {
+        let msg = format!("Here is the code.\n{}", code_block("", 40));
+        let packaged = package_reply(&msg, 30, 300, &NOTES);
+
+        assert!(packaged.preview.starts_with("Here is the code.\n\n"));
+        assert_eq!(packaged.files[1].filename, "code_1.txt");
+    }
+
+    #[test]
+    fn package_reply_preview_is_truncated() {
+        let msg = "word ".repeat(200);
+        let packaged = package_reply(&msg, 30, 50, &NOTES);
+        let first_line = packaged.preview.lines().next().unwrap();
+
+        assert!(char_len(first_line) <= 51);
+        assert!(first_line.ends_with("word…"));
+    }
 }
@@ -1,10 +1,70 @@
-use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt, prelude::*};
 
-pub fn init_tracing(log_level: &str) {
+use crate::shared::config::{LogFormat, Logging};
+
+/// 終了時にエクスポート待ちのトレースを送り切るため、`main` の終わりまで保持する
+pub struct TracingGuard {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer_provider.take()
+            && let Err(err) = provider.shutdown()
+        {
+            eprintln!("Failed to flush traces: {err}");
+        }
+    }
+}
+
+/// ログは標準エラーに出す。`export-memories` などのサブコマンドが標準出力に書く結果と混ざらないようにするため
+pub fn init_tracing(log_level: &str, logging: &Logging) -> Result<TracingGuard> {
     let env_filter = EnvFilter::new(log_level);
 
+    let fmt_layer: Box<dyn Layer<Registry> + Send + Sync> = match logging.format {
+        LogFormat::Text => fmt::layer().with_writer(std::io::stderr).boxed(),
+        LogFormat::Json => fmt::layer()
+            .with_writer(std::io::stderr)
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
+
+    let tracer_provider = match logging.otlp_endpoint.as_deref() {
+        Some(endpoint) => Some(tracer_provider(endpoint, &logging.service_name)?),
+        None => None,
+    };
+    let otel_layer = tracer_provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(logging.service_name.clone()))
+    });
+
     tracing_subscriber::registry()
-        .with(fmt::layer())
+        .with(fmt_layer)
+        .with(otel_layer)
         .with(env_filter)
         .init();
+
+    Ok(TracingGuard { tracer_provider })
+}
+
+fn tracer_provider(endpoint: &str, service_name: &str) -> Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .context("Failed to build OTLP exporter")?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
 }
@@ -0,0 +1,182 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+const NAMESPACE: &str = "nekoai";
+
+/// 埋め込み・検索・生成の所要時間のバケット（秒）。生成は数十秒かかることがある
+const STAGE_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// プロセス全体で共有するメトリクス。`tracing` と同じく、どの層からも記録できるようにグローバルに持つ
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    chat_requests: IntCounterVec,
+    commands: IntCounterVec,
+    stage_duration: HistogramVec,
+    errors: IntCounterVec,
+    promotion_failures: IntCounterVec,
+    tokens: IntCounterVec,
+    pub promotion_queue_depth: IntGauge,
+    pub short_term_channels: IntGauge,
+    pub short_term_messages: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some(NAMESPACE.to_string()), None)
+            .expect("metric namespace is valid");
+
+        let metrics = Self {
+            chat_requests: IntCounterVec::new(
+                Opts::new("chat_requests_total", "Chat requests by outcome"),
+                &["outcome"],
+            )
+            .expect("metric is valid"),
+            commands: IntCounterVec::new(
+                Opts::new("commands_total", "Executed commands"),
+                &["command"],
+            )
+            .expect("metric is valid"),
+            stage_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "stage_duration_seconds",
+                    "Duration of the embed, search and generate stages",
+                )
+                .buckets(STAGE_BUCKETS.to_vec()),
+                &["stage"],
+            )
+            .expect("metric is valid"),
+            errors: IntCounterVec::new(
+                Opts::new("errors_total", "Errors by AppError variant"),
+                &["kind"],
+            )
+            .expect("metric is valid"),
+            promotion_failures: IntCounterVec::new(
+                Opts::new(
+                    "promotion_failures_total",
+                    "Failed midterm promotions; permanent ones were given up",
+                ),
+                &["stage"],
+            )
+            .expect("metric is valid"),
+            tokens: IntCounterVec::new(
+                Opts::new("tokens_total", "Tokens used for generation"),
+                &["kind"],
+            )
+            .expect("metric is valid"),
+            promotion_queue_depth: IntGauge::new(
+                "promotion_queue_depth",
+                "Midterm promotions waiting for retry",
+            )
+            .expect("metric is valid"),
+            short_term_channels: IntGauge::new(
+                "short_term_channels",
+                "Channels with short-term conversations",
+            )
+            .expect("metric is valid"),
+            short_term_messages: IntGauge::new(
+                "short_term_messages",
+                "Messages held in short-term memory",
+            )
+            .expect("metric is valid"),
+            registry,
+        };
+
+        for collector in [
+            Box::new(metrics.chat_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(metrics.commands.clone()),
+            Box::new(metrics.stage_duration.clone()),
+            Box::new(metrics.errors.clone()),
+            Box::new(metrics.promotion_failures.clone()),
+            Box::new(metrics.tokens.clone()),
+            Box::new(metrics.promotion_queue_depth.clone()),
+            Box::new(metrics.short_term_channels.clone()),
+            Box::new(metrics.short_term_messages.clone()),
+        ] {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        metrics
+    }
+
+    pub fn record_request(&self, success: bool) {
+        let outcome = if success { "success" } else { "error" };
+        self.chat_requests.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_command(&self, command: &str) {
+        self.commands.with_label_values(&[command]).inc();
+    }
+
+    pub fn record_error(&self, kind: &str) {
+        self.errors.with_label_values(&[kind]).inc();
+    }
+
+    /// `permanent` は再試行を諦めた場合
+    pub fn record_promotion_failure(&self, permanent: bool) {
+        let stage = if permanent { "permanent" } else { "initial" };
+        self.promotion_failures.with_label_values(&[stage]).inc();
+    }
+
+    pub fn promotion_failures(&self, permanent: bool) -> u64 {
+        let stage = if permanent { "permanent" } else { "initial" };
+        self.promotion_failures.with_label_values(&[stage]).get()
+    }
+
+    pub fn record_tokens(&self, input_tokens: u64, output_tokens: u64) {
+        self.tokens
+            .with_label_values(&["input"])
+            .inc_by(input_tokens);
+        self.tokens
+            .with_label_values(&["output"])
+            .inc_by(output_tokens);
+    }
+
+    /// 破棄されるか `observe_duration` を呼んだ時点で所要時間を記録する
+    pub fn stage_timer(&self, stage: &str) -> HistogramTimer {
+        self.stage_duration
+            .with_label_values(&[stage])
+            .start_timer()
+    }
+
+    /// Prometheusのテキスト形式
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {err}");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_recorded_metrics_with_namespace() {
+        let metrics = Metrics::new();
+        metrics.record_request(true);
+        metrics.record_error("Embedding");
+        metrics.record_tokens(10, 3);
+        metrics.stage_timer("generate").observe_duration();
+
+        let text = metrics.encode();
+        assert!(text.contains(r#"nekoai_chat_requests_total{outcome="success"} 1"#));
+        assert!(text.contains(r#"nekoai_errors_total{kind="Embedding"} 1"#));
+        assert!(text.contains(r#"nekoai_tokens_total{kind="input"} 10"#));
+        assert!(text.contains(r#"nekoai_stage_duration_seconds_count{stage="generate"} 1"#));
+    }
+}
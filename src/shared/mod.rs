@@ -1,3 +1,6 @@
 pub mod config;
 pub mod discord_utils;
 pub mod logger;
+pub mod metrics;
+pub mod secret;
+pub mod shutdown;
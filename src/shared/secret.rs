@@ -0,0 +1,72 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+
+/// ログやエラーに出してはいけない値。`Debug` と `Display` では中身を伏せる
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// 外部のAPIに渡す時だけ使う
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// シークレットのファイル（Dockerのsecretsなど）を読む。末尾の改行は値に含めない
+pub fn read_secret_file(path: &str) -> std::io::Result<Secret> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(Secret::new(contents.trim_end_matches(['\r', '\n'])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_never_reveals_the_value() {
+        let secret = Secret::new("super-secret-token");
+
+        assert_eq!(format!("{secret}"), "[REDACTED]");
+        assert!(!format!("{secret:?}").contains("super-secret-token"));
+        assert!(!format!("{:#?}", Some(&secret)).contains("super-secret-token"));
+        assert_eq!(secret.expose(), "super-secret-token");
+    }
+
+    #[test]
+    fn secret_file_is_read_without_trailing_newline() {
+        let path = crate::test_support::temp_path();
+        std::fs::write(&path, "token-from-file\n").unwrap();
+
+        let secret = read_secret_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(secret.expose(), "token-from-file");
+
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,228 @@
+//! テストで共有するフェイク実装とヘルパー
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    application::traits::{
+        ai_client::AIClient, long_term_store::LongTermStore, promotion_queue::PromotionQueue,
+    },
+    models::{
+        health::CollectionStats,
+        memory::{
+            ChatMessage, Completion, LongTermMemory, MemoryExport, MemoryScope, MidTermMemory,
+            PendingPromotion, TokenUsage,
+        },
+    },
+};
+
+/// テストごとに重ならない一時パス。ファイルとしてもディレクトリとしても使える
+pub fn temp_path() -> PathBuf {
+    std::env::temp_dir().join(format!("neko_ai_test_{}", uuid::Uuid::new_v4()))
+}
+
+/// 決まった応答と4次元の埋め込みを返すAIクライアント。応答は既定では空
+#[derive(Default)]
+pub struct FakeAI {
+    fail_embed: bool,
+    embed_calls: AtomicUsize,
+    /// ストリーミングでは1つずつ送り、そうでなければつなげて返す
+    reply: Vec<&'static str>,
+}
+
+impl FakeAI {
+    /// 応答の使用トークン数は、入力10・出力は `reply` の数
+    pub fn replying(reply: &[&'static str]) -> Self {
+        Self {
+            reply: reply.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn completion(&self) -> Completion {
+        Completion {
+            content: self.reply.concat(),
+            usage: TokenUsage {
+                input_tokens: 10,
+                output_tokens: self.reply.len() as u64,
+            },
+        }
+    }
+
+    pub fn failing_embed() -> Self {
+        Self {
+            fail_embed: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn embed_calls(&self) -> usize {
+        self.embed_calls.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl AIClient for FakeAI {
+    async fn generate(
+        &self,
+        _prompt: ChatMessage,
+        _history: Vec<ChatMessage>,
+    ) -> Result<Completion> {
+        Ok(self.completion())
+    }
+
+    async fn generate_streaming(
+        &self,
+        _prompt: ChatMessage,
+        _history: Vec<ChatMessage>,
+        deltas: UnboundedSender<String>,
+    ) -> Result<Completion> {
+        for part in &self.reply {
+            let _ = deltas.send(part.to_string());
+        }
+        Ok(self.completion())
+    }
+
+    async fn embed(&self, _text: String) -> Result<Vec<f32>> {
+        self.embed_calls.fetch_add(1, Ordering::Relaxed);
+        if self.fail_embed {
+            anyhow::bail!("embedding API unavailable");
+        }
+        Ok(vec![0.0; 4])
+    }
+
+    async fn ping_generation(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 書き込み系の操作を順に `events` に記録する長期記憶ストア
+#[derive(Default)]
+pub struct FakeStore {
+    /// `list_memories` が返す記憶
+    pub memories: MemoryExport,
+    /// `collection_stats` が返す統計
+    pub stats: Vec<CollectionStats>,
+    /// 記憶の保存を失敗させる
+    pub fail_store: bool,
+    pub events: Mutex<Vec<String>>,
+}
+
+impl FakeStore {
+    pub fn events(&self) -> Vec<String> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn record(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn stored(&self, event: String) -> Result<()> {
+        if self.fail_store {
+            anyhow::bail!("Qdrant unavailable");
+        }
+        self.record(event);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LongTermStore for FakeStore {
+    async fn store_longterm(&self, memory: LongTermMemory, _embedding: Vec<f32>) -> Result<()> {
+        self.stored(format!("longterm {}", memory.id))
+    }
+
+    async fn store_midterm(&self, memory: MidTermMemory, _embedding: Vec<f32>) -> Result<()> {
+        self.stored(format!("midterm {}", memory.id))
+    }
+
+    async fn search_longterm(
+        &self,
+        _embedding: Vec<f32>,
+        _user_id: u64,
+        _scope: MemoryScope,
+        _limit: u64,
+    ) -> Result<Vec<LongTermMemory>> {
+        Ok(Vec::new())
+    }
+
+    async fn search_midterm(
+        &self,
+        _embedding: Vec<f32>,
+        _user_id: u64,
+        _scope: MemoryScope,
+        _limit: u64,
+    ) -> Result<Vec<MidTermMemory>> {
+        Ok(Vec::new())
+    }
+
+    async fn delete_expired_midterm(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn delete_midterm_turn(&self, _user_id: u64, _turn_id: u64) -> Result<()> {
+        Ok(())
+    }
+
+    async fn delete_guild(&self, _guild_id: u64) -> Result<()> {
+        Ok(())
+    }
+
+    async fn collection_stats(&self) -> Result<Vec<CollectionStats>> {
+        Ok(self.stats.clone())
+    }
+
+    async fn list_memories(&self, _user_id: Option<u64>) -> Result<MemoryExport> {
+        Ok(self.memories.clone())
+    }
+
+    async fn recreate_collections(&self) -> Result<()> {
+        self.record("recreate".to_string());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct FakeQueue {
+    pub entries: Mutex<Vec<PendingPromotion>>,
+}
+
+#[async_trait]
+impl PromotionQueue for FakeQueue {
+    async fn enqueue(&self, promotion: PendingPromotion) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|p| p.memory.id != promotion.memory.id);
+        entries.push(promotion);
+        Ok(())
+    }
+
+    async fn due(&self, now: i64) -> Vec<PendingPromotion> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .filter(|p| p.next_attempt_at <= now)
+            .cloned()
+            .collect()
+    }
+
+    async fn remove(&self, memory_id: &str) -> Result<()> {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|p| p.memory.id != memory_id);
+        Ok(())
+    }
+
+    async fn depth(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}